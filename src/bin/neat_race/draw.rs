@@ -0,0 +1,148 @@
+use std::time::Instant;
+
+use macroquad::{miniquad::conf::Platform, window::*, shapes::*, text::draw_text};
+use macroquad::prelude::{Color, BLACK, DARKGRAY, ORANGE, PURPLE, RED, WHITE, YELLOW};
+
+use neat_race::car::{Car, Termination};
+use neat_race::centreline::Direction;
+use neat_race::geometry::Point;
+use neat_race::track::Track;
+use neat_race::Simulation;
+
+use crate::{WINDOW_SIZE, TEXT_COOLDOWN};
+
+
+/// Closed polygon outline.
+pub fn draw_polygon(points: &[Point], thickness: f32, color: Color) {
+    for i in 0..points.len() {
+        draw_line(
+            points[i].x, points[i].y, 
+            points[(i+1)%points.len()].x, points[(i+1)%points.len()].y, 
+            thickness, color
+        );
+    }
+}
+
+pub fn draw_track(t: &Track) {
+    draw_polygon(&t.inner, 3.0, BLACK);
+    draw_polygon(&t.outer, 3.0, BLACK);
+    for (a, b) in &t.gates { draw_line(a.x, a.y, b.x, b.y, 1.0, DARKGRAY); }
+    draw_line(t.start_gate.0.x, t.start_gate.0.y, t.start_gate.1.x, t.start_gate.1.y, 5.0, WHITE);
+}
+
+pub fn draw_car(car: &Car, color: Color) {
+    draw_polygon(&car.points, 4.0, color);
+}
+
+/// Cross where every car of current episode ended, colored by reason.
+pub fn draw_terminations(sim: &Simulation) {
+    for c in &sim.cars {
+        let color = match c.termination {
+            Some(Termination::InnerWall) | Some(Termination::OuterWall) => RED,
+            Some(Termination::Stall) => YELLOW,
+            Some(Termination::WrongWay) => PURPLE,
+            Some(Termination::SpinOut) => ORANGE,
+            _ => continue,
+        };
+        let p = c.get_position();
+        draw_line(p.x - 5.0, p.y - 5.0, p.x + 5.0, p.y + 5.0, 2.0, color);
+        draw_line(p.x - 5.0, p.y + 5.0, p.x + 5.0, p.y - 5.0, 2.0, color);
+    }
+}
+
+pub fn contrasting_color(slice: &[usize], element: usize) -> Color {
+    let len = slice.len();
+    if len == 0 {
+        return Color::from_rgba(255,255,255,255); // Or some default color
+    }
+
+    let index = slice.iter().position(|&x| x == element).unwrap_or(0);
+
+    let hue = (index as f32 / len as f32) * 360.0;
+
+    //  saturation and value are constants, 
+    // make sure to have high Value for contrast.
+
+    hsv_to_rgb(hue, 0.8, 0.9)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> Color {
+    let h_i = (h / 60.0) as i32;
+    let f = h / 60.0 - h_i as f32;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - f * s);
+    let t = v * (1.0 - (1.0 - f) * s);
+
+    let (r, g, b) = match h_i {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        5 => (v, p, q),
+        _ => (0.0, 0.0, 0.0), // Should not happen, h is always in range [0, 360)
+    };
+
+    Color::new(r, g, b, 1.0) //alpha = 255
+}
+
+
+pub fn draw_cooldown(clock: Instant, text: &str, size: f32) {
+    if clock.elapsed().as_secs_f32() < TEXT_COOLDOWN {
+        draw_text(
+            text,
+            (WINDOW_SIZE.0/2) as f32 - (text.len() as f32*size/5.0), WINDOW_SIZE.1 as f32 / (size / 92.0), 
+            size, 
+            Color::from_rgba(255, 255, 255, 255 - (((clock.elapsed().as_secs_f32())-TEXT_COOLDOWN+1.0) * 255.0).max(0.0) as u8)
+        );
+    }
+}
+
+pub struct FrameTimeAnalyzer {
+    frame: Vec<f32>,
+    s_time: f32,
+}
+
+impl FrameTimeAnalyzer {
+    pub fn new(length: usize) -> Self {
+        FrameTimeAnalyzer {
+            frame: vec![0.; length],
+            s_time: 0.,
+        }
+    }
+
+    pub fn add_frame_time(&mut self, time: f32) {
+        self.frame.pop();
+        self.frame.insert(0, time);
+    }
+
+    pub fn smooth_frame_time(&mut self) -> &f32 {
+        self.s_time = self.frame.iter().sum::<f32>() / (self.frame.len() as f32);
+        &self.s_time
+    }
+}
+
+/// Lap progress of the leading car, for HUDs.
+pub fn leader_text(sim: &Simulation) -> String {
+    let l = sim.track.centreline.length;
+    let lead = sim.leader().map_or("LEAD: -".to_string(), |c| format!("LEAD: lap {} + {:.0}% | GATES: {}", c.laps(l), c.lap_fraction(l) * 100.0, c.gates));
+    let best = sim.cars.iter().filter_map(|c| c.best_lap() ).reduce(f32::min);
+    let lead = lead + &best.map_or(String::new(), |t| format!(" | BEST: {:.2}s", t));
+    if sim.track.direction == Direction::Reverse { lead + " (reverse)" } else { lead }
+}
+
+pub fn conf() -> Conf {
+    let p = Platform {
+        linux_backend: macroquad::miniquad::conf::LinuxBackend::X11WithWaylandFallback,
+        ..Default::default()
+    };
+    Conf {
+        window_title: String::from("Macroquad"),
+        window_width: WINDOW_SIZE.0 as i32,
+        window_height: WINDOW_SIZE.1 as i32,
+        fullscreen: false,
+        sample_count: 16,
+        platform: p,
+        ..Default::default()
+    }
+}
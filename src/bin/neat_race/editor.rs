@@ -0,0 +1,166 @@
+use std::path::Path;
+use std::time::Instant;
+
+use macroquad::{window::*, shapes::*, text::draw_text};
+use macroquad::prelude::{is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, mouse_position, mouse_wheel,
+    KeyCode, MouseButton, BLACK, DARKGRAY, GRAY, RED, WHITE};
+
+use neat_race::geometry::{distance, project_on_segment, Point};
+use neat_race::track::{check_track, from_centreline, Track, TrackError};
+use neat_race::Config;
+
+use crate::{Args, fail};
+use crate::draw::{conf, draw_cooldown, draw_polygon, draw_track};
+
+// how close to a control point mouse has to be to grab it
+const GRAB_RADIUS: f32 = 15.0;
+// width change of one mouse wheel step
+const WIDTH_STEP: f32 = 5.0;
+
+
+/// Draw track by hand, starting from `--track` if given, saved into `--out` (or that track).
+pub fn run(args: &Args, config: Config) {
+    let editor = match args.value("--track") {
+        Some(p) => {
+            let t = Track::load(&config, Path::new(&p)).unwrap_or_else(|e| fail(&format!("can't load track {}: {}", p, e)));
+            // centreline is smoothed `res` times, so every 2^res-th point of it gives roughly the same track back,
+            // only a bit rounder
+            let step = 1 << config.track.res;
+            Editor::new(t.centreline.points.iter().step_by(step).cloned().collect(), t.widths.iter().step_by(step).cloned().collect())
+        }
+        None => Editor::new(vec![], vec![]),
+    };
+    let out = args.value("--out").or_else(|| args.value("--track")).unwrap_or_else(|| "track.json".to_string());
+
+    macroquad::Window::from_config(conf(), editor_loop(config, editor, out));
+}
+
+/// Control points of centreline with track width at each of them, and track built from them.
+struct Editor {
+    points: Vec<Point>,
+    widths: Vec<f32>,
+    dragging: Option<usize>,
+    preview: Preview,
+}
+
+/// Track built from control points, or smoothed boundaries and why they aren't one.
+enum Preview {
+    TooFew,
+    Invalid(Vec<Point>, Vec<Point>, TrackError),
+    Valid(Track),
+}
+
+impl Editor {
+    fn new(points: Vec<Point>, widths: Vec<f32>) -> Self {
+        Self { points, widths, dragging: None, preview: Preview::TooFew }
+    }
+
+    fn grab(&self, p: &Point) -> Option<usize> {
+        (0..self.points.len()).filter(|i| distance(&self.points[*i], p) < GRAB_RADIUS )
+            .min_by(|a, b| distance(&self.points[*a], p).total_cmp(&distance(&self.points[*b], p)) )
+    }
+
+    // new point splits the closest edge, so the loop can be extended anywhere
+    fn insert(&mut self, p: Point, width: f32) -> usize {
+        let n = self.points.len();
+        let i = if n < 3 { n } else {
+            let dst = |i: &usize| distance(&p, &project_on_segment(&p, &self.points[*i], &self.points[(*i+1) % n]).0);
+            (0..n).min_by(|a, b| dst(a).total_cmp(&dst(b)) ).unwrap() + 1
+        };
+        self.points.insert(i, p);
+        self.widths.insert(i, width);
+        i
+    }
+
+    fn rebuild(&mut self, config: &Config) {
+        if self.points.len() < 3 { self.preview = Preview::TooFew; return; }
+        let (inner, outer) = from_centreline(&self.points, &self.widths, config.track.res);
+        self.preview = match check_track(&inner, &outer, config.track.min_width) {
+            Ok(()) => Preview::Valid(Track::new(config, (inner, outer), config.seed.unwrap_or(0), None)),
+            Err(e) => Preview::Invalid(inner, outer, e),
+        };
+    }
+}
+
+async fn editor_loop(config: Config, mut editor: Editor, out: String) {
+    let mut clock_save: Option<Instant> = None;
+    editor.rebuild(&config);
+
+    loop {
+        if is_key_pressed(KeyCode::Q) {return;}
+        let (x, y) = mouse_position();
+        let mouse = Point::new(x, y);
+        let hovered = editor.grab(&mouse);
+        let mut changed = false;
+
+        // left grabs a point or places a new one, right removes, wheel changes width
+        if is_mouse_button_pressed(MouseButton::Left) {
+            editor.dragging = Some(hovered.unwrap_or_else(|| editor.insert(mouse.clone(), config.track.width) ));
+            changed = true;
+        }
+        if !is_mouse_button_down(MouseButton::Left) { editor.dragging = None; }
+        if let Some(i) = editor.dragging {
+            if distance(&editor.points[i], &mouse) > 0.0 { editor.points[i] = mouse.clone(); changed = true; }
+        }
+        if let (true, Some(i)) = (is_mouse_button_pressed(MouseButton::Right), hovered) {
+            editor.points.remove(i);
+            editor.widths.remove(i);
+            editor.dragging = None;
+            changed = true;
+        }
+        let wheel = mouse_wheel().1;
+        if let (true, Some(i)) = (wheel != 0.0, hovered) {
+            editor.widths[i] = (editor.widths[i] + wheel.signum() * WIDTH_STEP).max(WIDTH_STEP);
+            changed = true;
+        }
+        if is_key_pressed(KeyCode::C) {
+            editor = Editor::new(vec![], vec![]);
+            changed = true;
+        }
+        if changed { editor.rebuild(&config); }
+
+        if is_key_pressed(KeyCode::S) {
+            match &editor.preview {
+                Preview::Valid(t) => match t.save(Path::new(&out)) {
+                    Ok(_) => { println!("track saved: {}", out); clock_save = Some(Instant::now()); }
+                    Err(e) => eprintln!("can't save track: {}", e),
+                },
+                Preview::Invalid(_, _, e) => eprintln!("can't save invalid track: {}", e),
+                Preview::TooFew => eprintln!("can't save track, it needs at least 3 points"),
+            }
+        }
+
+        // ----------------- DRAWING
+
+        clear_background(GRAY);
+
+        let status = match &editor.preview {
+            Preview::Valid(t) => {
+                draw_track(t);
+                let i = &t.info;
+                format!("OK | length: {:.0} | width: {:.0} min, {:.0} mean | curvature: {:.4} max", i.length, i.min_width, i.mean_width, i.max_curvature)
+            }
+            Preview::Invalid(inner, outer, e) => {
+                draw_polygon(inner, 3.0, RED);
+                draw_polygon(outer, 3.0, RED);
+                if let TrackError::TooNarrow { at, .. } = e { draw_circle_lines(at.x, at.y, 20.0, 3.0, RED); }
+                format!("INVALID: {}", e)
+            }
+            Preview::TooFew => "place at least 3 points".to_string(),
+        };
+
+        // control polygon with points sized by their width
+        draw_polygon(&editor.points, 1.0, DARKGRAY);
+        for (i, (p, w)) in editor.points.iter().zip(&editor.widths).enumerate() {
+            let color = if Some(i) == hovered || Some(i) == editor.dragging { WHITE } else { BLACK };
+            draw_circle(p.x, p.y, 5.0, color);
+            draw_circle_lines(p.x, p.y, w / 2.0, 1.0, color);
+        }
+
+        draw_text("LMB place/drag | RMB remove | wheel width | S save | C clear | Q quit", 10.0, 30.0, 30.0, DARKGRAY);
+        draw_text(&status, 10.0, 60.0, 30.0, DARKGRAY);
+        draw_text(&("Out: ".to_owned() + &out), 10.0, 90.0, 30.0, DARKGRAY);
+        if let Some(c) = clock_save { draw_cooldown(c, "SAVED TRACK", 200.0); }
+        next_frame().await;
+    }
+}
@@ -0,0 +1,42 @@
+use neat_race::geometry::mix_seed;
+use neat_race::track::Track;
+use neat_race::{Config, Simulation};
+
+use crate::{Args, check_agents, load_agents, seed_or_random};
+
+
+/// Runs genomes on `--tracks` random tracks, printing stats of every track and summary.
+pub fn run(args: &Args, config: Config) {
+    let (_, agents) = load_agents(args);
+    check_agents(&agents, &config);
+    let tracks = args.parsed::<usize>("--tracks").unwrap_or(10);
+    let seed = seed_or_random(&config);
+    let dt = config.population.dt;
+
+    // (fitness, laps, survived) of every agent on every track
+    let mut all: Vec<(f32, f32, bool)> = vec![];
+    for t in 0..tracks {
+        let track_seed = mix_seed(seed, t as u64);
+        let mut sim = Simulation::with_agents(config.clone(), agents.clone(), Track::from_seed(&config, track_seed));
+        sim.run_episode(dt);
+
+        let results: Vec<(f32, f32, bool)> = sim.cars.iter().map(|c| (sim.fitness_of(c), c.progress / sim.track.centreline.length, c.alive) ).collect();
+        let (best, mean) = fitness_stats(&results);
+        println!("track {:>3} | seed: {:>20} | survived: {:>4}/{} | best: {:>10.1} | mean: {:>10.1} | laps: {:>5.2}",
+            t, track_seed, sim.alive_sum, results.len(), best, mean, results.iter().map(|r| r.1 ).fold(0.0, f32::max));
+        all.extend(results);
+    }
+
+    let (best, mean) = fitness_stats(&all);
+    println!("total     | tracks: {} | genomes: {} | survived: {:.1}% | best: {:.1} | mean: {:.1} | mean laps: {:.2}",
+        tracks, agents.len(),
+        all.iter().filter(|r| r.2 ).count() as f32 / all.len().max(1) as f32 * 100.0,
+        best, mean,
+        all.iter().map(|r| r.1 ).sum::<f32>() / all.len().max(1) as f32);
+}
+
+fn fitness_stats(results: &[(f32, f32, bool)]) -> (f32, f32) {
+    let best = results.iter().map(|r| r.0 ).fold(0.0, f32::max);
+    let mean = results.iter().map(|r| r.0 ).sum::<f32>() / results.len().max(1) as f32;
+    (best, mean)
+}
@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use neat_race::rusty_neat::NN;
+use neat_race::geometry::mix_seed;
+use neat_race::track::{generate, Track};
+use neat_race::genome::Genome;
+use neat_race::{Checkpoint, Config};
+
+mod draw;
+mod train;
+mod evaluate;
+mod replay;
+mod editor;
+
+
+
+pub const WINDOW_SIZE: (u32, u32) = (1920, 1080);//(1600, 900);
+pub const TEXT_COOLDOWN: f32 = 2.0;  // text fade after that time
+pub const TURBO_SPEEDS: [usize; 7] = [1, 2, 4, 8, 16, 32, 0];  // steps per frame, 0 is MAX (cars aren't drawn)
+pub const TURBO_MAX_FRAME: f32 = 0.05;  // wall time spent simulating per frame at MAX speed
+pub const SLOW_SPEEDS: [usize; 4] = [1, 2, 4, 8];  // frames per step in slow motion
+
+const USAGE: &str = "\
+usage: neat_race [COMMAND] [OPTIONS]
+
+commands:
+  train       evolve population (default)
+                --headless              no window
+                --generations <n>       stop after n generations (headless only)
+                --load <checkpoint>     continue saved run, with its own config and seed
+  evaluate    run saved genomes on random tracks and print stats
+                --genome <file>         can be repeated
+                --checkpoint <file>     whole saved population
+                --tracks <n>            amount of tracks, 10 by default
+  replay      watch saved genomes drive
+                --genome <file>, --checkpoint <file>
+                --track <file>          otherwise random track is generated
+                                        R restarts, T generates new track, K saves it
+  gen-track   write track file
+                --out <file>            track_<seed>.json by default, bincode unless *.json
+  edit        draw track by hand
+                --track <file>          start from this track
+                --out <file>            --track or track.json by default
+
+options:
+  --config <file>     TOML settings, see config.toml
+  --set <key=value>   override single setting, can be repeated
+  --seed <n>          master seed
+  --help";
+
+
+// options every command takes
+const COMMON_OPTIONS: [&str; 4] = ["--config", "--set", "--seed", "--help"];
+
+/// Options of command, None if there is no such command.
+fn command_options(command: &str) -> Option<&'static [&'static str]> {
+    Some(match command {
+        "train" => &["--headless", "--generations", "--load"],
+        "evaluate" => &["--genome", "--checkpoint", "--tracks"],
+        "replay" => &["--genome", "--checkpoint", "--track"],
+        "gen-track" => &["--out"],
+        "edit" => &["--track", "--out"],
+        _ => return None,
+    })
+}
+
+
+pub struct Args {
+    list: Vec<String>,
+}
+
+impl Args {
+    pub fn flag(&self, name: &str) -> bool {
+        self.list.iter().any(|a| a == name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<String> {
+        self.list.iter().position(|a| a == name).and_then(|i| self.list.get(i+1)).cloned()
+    }
+
+    /// Every value of repeated option.
+    pub fn values(&self, name: &str) -> Vec<String> {
+        self.list.windows(2).filter(|w| w[0] == name).map(|w| w[1].clone()).collect()
+    }
+
+    /// First `--option` that isn't in `known`, so typos don't get silently ignored.
+    pub fn unknown(&self, known: &[&str]) -> Option<&String> {
+        self.list.iter().find(|a| a.starts_with("--") && !known.contains(&a.as_str()) && !COMMON_OPTIONS.contains(&a.as_str()) )
+    }
+
+    pub fn parsed<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.value(name).map(|v| v.parse::<T>().unwrap_or_else(|_| fail(&format!("{} expects a number", name))))
+    }
+}
+
+pub fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
+}
+
+
+fn main() {
+    let list: Vec<String> = std::env::args().skip(1).collect();
+    // no command means train, so plain `cargo run` opens the window as before
+    let (command, list) = match list.first() {
+        Some(c) if !c.starts_with("--") => (c.clone(), list[1..].to_vec()),
+        _ => ("train".to_string(), list),
+    };
+    let args = Args { list };
+    if args.flag("--help") { println!("{}", USAGE); return; }
+    let known = command_options(&command).unwrap_or_else(|| fail(&format!("unknown command: {}\n\n{}", command, USAGE)));
+    if let Some(a) = args.unknown(known) { fail(&format!("unknown option for {}: {}\n\n{}", command, a, USAGE)); }
+
+    let mut config = Config::load(args.value("--config").as_deref().map(Path::new), &args.values("--set"))
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    if let Some(s) = args.parsed::<u64>("--seed") {
+        config.seed = Some(s);
+        if let Err(e) = config.validate() { fail(&e.to_string()); }
+    }
+
+    match command.as_str() {
+        "train" => train::run(&args, config),
+        "evaluate" => evaluate::run(&args, config),
+        "replay" => replay::run(&args, config),
+        "gen-track" => gen_track(&args, config),
+        "edit" => editor::run(&args, config),
+        _ => unreachable!(),
+    }
+}
+
+/// Seed from config, or random one (printed, so it can be repeated).
+pub fn seed_or_random(config: &Config) -> u64 {
+    config.seed.unwrap_or_else(|| {
+        let s = rand::random::<u32>() as u64;
+        println!("seed: {}", s);
+        s
+    })
+}
+
+/// Genomes from every `--genome` and `--checkpoint`, with labels.
+pub fn load_agents(args: &Args) -> (Vec<String>, Vec<NN>) {
+    let (mut labels, mut agents) = (vec![], vec![]);
+    for p in args.values("--genome") {
+        let g = Genome::load(Path::new(&p)).unwrap_or_else(|e| fail(&format!("can't load genome {}: {}", p, e)));
+        let name = Path::new(&p).file_stem().map_or(p.clone(), |s| s.to_string_lossy().to_string());
+        labels.push(format!("{} (gen {}, fitness {:.1})", name, g.generation, g.fitness));
+        agents.push(g.nn);
+    }
+    for p in args.values("--checkpoint") {
+        let c = Checkpoint::load(Path::new(&p)).unwrap_or_else(|e| fail(&format!("can't load checkpoint {}: {}", p, e)));
+        labels.extend((0..c.agents.len()).map(|i| format!("gen {} #{}", c.generation, i) ));
+        agents.extend(c.agents);
+    }
+    if agents.is_empty() { fail("no genomes given, use --genome or --checkpoint"); }
+    (labels, agents)
+}
+
+/// Checks that networks fit sensors from config.
+pub fn check_agents(agents: &[NN], config: &Config) {
+    // size includes bias node
+    let inputs = config.network.rays + 2;
+    if let Some(a) = agents.iter().find(|a| a.size != (inputs + 1, 2)) {
+        fail(&format!("genome has {} inputs and {} outputs, config needs {} and 2, check network.rays", a.size.0 - 1, a.size.1, inputs));
+    }
+}
+
+fn gen_track(args: &Args, config: Config) {
+    let seed = mix_seed(seed_or_random(&config), 0);
+    let (boundaries, rejected) = generate(&config.track, seed);
+    for (i, e) in rejected.iter().enumerate() { eprintln!("attempt {} rejected: {}", i + 1, e); }
+    let out = args.value("--out").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(format!("track_{}.json", seed)));
+
+    let t = Track::new(&config, boundaries, seed, Some(config.track.generator));
+    let i = &t.info;
+    eprintln!("length: {:.0} | width: {:.0} min, {:.0} mean | curvature: {:.4} mean, {:.4} max", i.length, i.min_width, i.mean_width, i.mean_curvature, i.max_curvature);
+    t.save(&out).unwrap_or_else(|e| fail(&format!("can't write track: {}", e)));
+    println!("{}", out.display());
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+use std::time::Instant;
+
+use macroquad::{window::*, text::draw_text};
+use macroquad::prelude::{GRAY, DARKGRAY, is_key_pressed, KeyCode};
+
+use neat_race::rusty_neat::NN;
+use neat_race::geometry::mix_seed;
+use neat_race::track::Track;
+use neat_race::{Config, Simulation};
+
+use crate::{Args, check_agents, fail, load_agents, seed_or_random, WINDOW_SIZE};
+use crate::draw::{conf, contrasting_color, draw_car, draw_track, leader_text};
+
+const LEGEND_MAX: usize = 10;
+
+
+/// Watch genomes drive, episode restarts when every car is dead or time is out.
+pub fn run(args: &Args, config: Config) {
+    let (labels, agents) = load_agents(args);
+    check_agents(&agents, &config);
+
+    let track = match args.value("--track") {
+        Some(p) => Track::load(&config, Path::new(&p)).unwrap_or_else(|e| fail(&format!("can't load track {}: {}", p, e))),
+        None => Track::from_seed(&config, mix_seed(seed_or_random(&config), 0)),
+    };
+
+    macroquad::Window::from_config(conf(), viewer(config, labels, agents, track));
+}
+
+async fn viewer(config: Config, labels: Vec<String>, agents: Vec<NN>, track: Track) {
+    let dt_static = config.population.dt;
+    let mut sim = Simulation::with_agents(config, agents, track);
+    let colors: Vec<usize> = (0..sim.cars.len()).collect();
+    let mut paused = false;
+    let mut dt_clock = Instant::now();
+
+    loop {
+        if is_key_pressed(KeyCode::Q) {return;}
+        if is_key_pressed(KeyCode::Space) { paused = !paused; }
+        if is_key_pressed(KeyCode::R) { sim.restart(); }
+        if is_key_pressed(KeyCode::K) {
+            let path = format!("track_{}.json", sim.track.info.seed);
+            match sim.track.save(Path::new(&path)) {
+                Ok(_) => println!("track saved: {}", path),
+                Err(e) => eprintln!("can't save track: {}", e),
+            }
+        }
+        if is_key_pressed(KeyCode::T) {
+            let s = rand::random::<u32>() as u64;
+            sim.set_track(Track::from_seed(&sim.config, s));
+        }
+
+        let dt = if sim.config.population.static_dt { dt_static } else { dt_clock.elapsed().as_secs_f32() };
+        dt_clock = Instant::now();
+        if !paused {
+            if sim.generation_over() { sim.restart(); }
+            sim.step(dt);
+        }
+
+        clear_background(GRAY);
+        draw_track(&sim.track);
+        sim.cars.iter().enumerate().filter(|(_,c)| c.alive).for_each(|(i,c)| draw_car(c, contrasting_color(&colors, i)) );
+
+        draw_text(&("Track: ".to_owned() + &sim.track.info.seed.to_string()), 10.0, 30.0, 30.0, DARKGRAY);
+        // legend, only if it fits
+        if labels.len() <= LEGEND_MAX {
+            for (i, l) in labels.iter().enumerate() {
+                draw_text(l, 10.0, 60.0 + i as f32 * 25.0, 25.0, contrasting_color(&colors, i));
+            }
+        }
+        draw_text(&("Time: ".to_owned() + &(sim.elapsed() as u64).to_string() + " | Tick: " + &sim.tick.to_string()
+            + if paused { " PAUSED" } else { "" }), 10.0, WINDOW_SIZE.1 as f32 - 50.0, 30.0, DARKGRAY);
+        draw_text(&("SUM: ".to_owned() + &(sim.alive_sum).to_string()), WINDOW_SIZE.0 as f32 - 200.0, WINDOW_SIZE.1 as f32 - 10.0, 50.0, DARKGRAY);
+        draw_text(leader_text(&sim), WINDOW_SIZE.0 as f32 - 520.0, WINDOW_SIZE.1 as f32 - 60.0, 30.0, DARKGRAY);
+        next_frame().await;
+    }
+}
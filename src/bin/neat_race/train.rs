@@ -0,0 +1,248 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use macroquad::time::get_fps;
+
+use macroquad::{window::*, text::draw_text};
+use macroquad::prelude::{GRAY, DARKGRAY, is_key_pressed, KeyCode};
+
+use neat_race::{checkpoint, Checkpoint, Config, Simulation};
+use neat_race::stats::GenerationStats;
+use neat_race::validation::{ValidationResult, ValidationSuite};
+
+use crate::{Args, fail, WINDOW_SIZE, TURBO_SPEEDS, TURBO_MAX_FRAME, SLOW_SPEEDS};
+use crate::draw::{conf, contrasting_color, draw_car, draw_cooldown, draw_terminations, draw_track, leader_text, FrameTimeAnalyzer};
+
+
+pub fn run(args: &Args, mut config: Config) {
+    let sim = match args.value("--load").map(PathBuf::from) {
+        // checkpoint's config wins, so these would be silently ignored
+        Some(_) if ["--config", "--set", "--seed"].iter().any(|o| args.flag(o) ) =>
+            fail("--load continues with config and seed of the checkpoint, it can't be used with --config, --set or --seed"),
+        Some(path) => match Checkpoint::load(&path) {
+            Ok(c) => Simulation::from_checkpoint(c).unwrap_or_else(|e| fail(&e.to_string())),
+            Err(e) => fail(&format!("can't load checkpoint {}: {}", path.display(), e)),
+        },
+        None => {
+            // random seed is written to run's config, so run can be repeated
+            let seed = *config.seed.get_or_insert_with(|| rand::random::<u32>() as u64);
+            Simulation::new(config, seed).unwrap_or_else(|e| fail(&e.to_string()))
+        }
+    };
+    let run = Run::create(&sim);
+
+    if args.flag("--headless") {
+        // optional limit, otherwise trains until killed
+        headless(sim, run, args.parsed::<usize>("--generations"));
+    } else {
+        macroquad::Window::from_config(conf(), windowed(sim, run));
+    }
+}
+
+/// Output directory of training run, best fitness seen in it so far and validation tracks.
+struct Run {
+    dir: PathBuf,
+    best: f32,
+    validation: ValidationSuite,
+    last_validation: Option<ValidationResult>,
+}
+
+impl Run {
+    // every run gets its own directory with config it was started with
+    fn create(sim: &Simulation) -> Self {
+        let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        // pid tells apart runs with the same seed started in the same second, create_dir refuses to share one anyway
+        let root = Path::new(&sim.config.run.output_dir);
+        let dir = root.join(format!("{}_{}_{}", time, sim.seed, std::process::id()));
+        std::fs::create_dir_all(root).and_then(|_| std::fs::create_dir(&dir) ).and_then(|_| std::fs::create_dir(dir.join("champions")) )
+            .unwrap_or_else(|e| fail(&format!("can't create run directory {}: {}", dir.display(), e)));
+
+        let mut config = sim.config.clone();
+        config.seed = Some(sim.seed);
+        config.save(&dir.join("config.toml")).unwrap_or_else(|e| fail(&format!("can't write run config: {}", e)));
+        println!("seed: {} | output: {}", sim.seed, dir.display());
+        let validation = ValidationSuite::new(&sim.config).unwrap_or_else(|e| fail(&format!("can't load validation track: {}", e)));
+        Self { dir, best: f32::MIN, validation, last_validation: None }
+    }
+
+    // current track, to train or validate on it later
+    fn save_track(&self, sim: &Simulation) -> bool {
+        let dir = self.dir.join("tracks");
+        let path = dir.join(format!("gen_{:06}_{}.json", sim.generation, sim.track.info.seed));
+        match std::fs::create_dir_all(&dir).and_then(|_| sim.track.save(&path) ) {
+            Ok(_) => { println!("track saved: {}", path.display()); true }
+            Err(e) => { eprintln!("can't save track: {}", e); false }
+        }
+    }
+
+    fn save(&self, sim: &Simulation) -> bool {
+        match sim.checkpoint().save_rotating(&self.dir.join("checkpoints"), sim.config.run.checkpoint_keep) {
+            Ok(_) => true,
+            Err(e) => { eprintln!("can't save checkpoint: {}", e); false }
+        }
+    }
+
+    // logs finished generation, keeps its champion and breeds next one, bool is true if autosave happened
+    fn next_gen(&mut self, sim: &mut Simulation) -> (GenerationStats, bool) {
+        let mut stats = sim.stats();
+        let champion = sim.champion();
+        let every = sim.config.validation.every;
+        if every > 0 && sim.generation.is_multiple_of(every) {
+            stats.validation = Some(self.validation.evaluate(&sim.config, sim.fitness(), &champion.nn, sim.generation));
+            self.last_validation = stats.validation.clone();
+        }
+        if let Err(e) = stats.append(&self.dir.join("stats.jsonl")) { eprintln!("can't write stats: {}", e); }
+
+        let mut saved = champion.save(&self.dir.join("champions").join(format!("gen_{:06}.toml", sim.generation)));
+        if champion.fitness > self.best {
+            self.best = champion.fitness;
+            saved = saved.and(champion.save(&self.dir.join("best.toml")));
+        }
+        if let Err(e) = saved { eprintln!("can't save champion: {}", e); }
+
+        sim.next_gen();
+        (stats, sim.generation.is_multiple_of(sim.config.run.checkpoint_every) && self.save(sim))
+    }
+
+    // single step, moving to next gen first if needed
+    fn advance(&mut self, sim: &mut Simulation, dt: f32) -> bool {
+        let saved = sim.generation_over() && self.next_gen(sim).1;
+        sim.step(dt);
+        saved
+    }
+}
+
+fn headless(mut sim: Simulation, mut run: Run, generations: Option<usize>) {
+    // there is no frame time without window, so step is always static
+    while generations.is_none_or(|g| sim.generation < g) {
+        sim.run_episode(sim.config.population.dt);
+        let (stats, _) = run.next_gen(&mut sim);
+        println!("GEN: {:>5} | best: {:>10.1} | mean: {:>8.1} | hof: {:>10.1} | alive: {:>4} | species: {:>3} | {:.2}s",
+            stats.generation, stats.best_fitness, stats.mean_fitness, stats.hall_of_fame_best, stats.alive, stats.species_count, stats.wall_time);
+        if let Some(v) = &stats.validation { println!("VAL: {}", validation_text(v)); }
+    }
+}
+
+fn validation_text(v: &ValidationResult) -> String {
+    format!("gen {} | completed: {:.0}% | lap: {} | crashed: {:.0}%", v.generation, v.completion_rate * 100.0,
+        v.mean_lap_time.map_or("-".to_string(), |t| format!("{:.1}s", t)), v.crash_rate * 100.0)
+}
+
+async fn windowed(mut sim: Simulation, mut run: Run) {
+    let mut clock_save: Option<Instant> = None;
+    let mut clock_read: Option<Instant> = None;
+    let mut clock_track: Option<Instant> = None;
+
+    let mut dt: f32;
+    let mut dt_clock = Instant::now();
+    let mut fta = FrameTimeAnalyzer::new(32);
+    let mut turbo: usize = 0;  // index in TURBO_SPEEDS
+    let mut slow: usize = 0;  // index in SLOW_SPEEDS
+    let mut paused = false;
+    let mut show_deaths = true;
+    let mut frame_n: usize = 0;
+    
+    loop {
+        //println!("{:?}", neat.agents[0]);
+        if is_key_pressed(KeyCode::Equal) { turbo = (turbo + 1).min(TURBO_SPEEDS.len() - 1); }
+        if is_key_pressed(KeyCode::Minus) { turbo = turbo.saturating_sub(1); }
+        if is_key_pressed(KeyCode::M) { slow = (slow + 1) % SLOW_SPEEDS.len(); }
+        if is_key_pressed(KeyCode::C) { show_deaths = !show_deaths; }
+        if is_key_pressed(KeyCode::Space) { paused = !paused; }
+        // slow motion overrides turbo
+        let steps = if slow == 0 { TURBO_SPEEDS[turbo] } else { 1 };
+        frame_n += 1;
+
+        // frame time makes no sense with multiple steps per frame or slow motion
+        dt = if sim.config.population.static_dt || steps != 1 || slow != 0 { sim.config.population.dt } else { dt_clock.elapsed().as_secs_f32() };
+        dt_clock = Instant::now();
+        if is_key_pressed(KeyCode::Q) {return;}
+        if is_key_pressed(KeyCode::S) && run.save(&sim) { clock_save = Some(Instant::now()); }
+        if is_key_pressed(KeyCode::K) && run.save_track(&sim) { clock_track = Some(Instant::now()); }
+        if is_key_pressed(KeyCode::L) {
+            let dir = run.dir.join("checkpoints");
+            match checkpoint::latest(&dir).map(|p| Checkpoint::load(&p).and_then(Simulation::from_checkpoint)) {
+                Some(Ok(s)) => { sim = s; clock_read = Some(Instant::now()); }
+                Some(Err(e)) => eprintln!("can't load checkpoint: {}", e),
+                None => eprintln!("no checkpoints in {}", dir.display()),
+            }
+        }
+
+        if paused {
+            // next step, or rest of generation
+            if is_key_pressed(KeyCode::N) && run.advance(&mut sim, dt) { clock_save = Some(Instant::now()); }
+            if is_key_pressed(KeyCode::G) {
+                // at the very end of generation this breeds the next one first and runs it instead
+                if run.advance(&mut sim, dt) { clock_save = Some(Instant::now()); }
+                while !sim.generation_over() { sim.step(dt); }
+            }
+        } else if frame_n.is_multiple_of(SLOW_SPEEDS[slow]) {
+            let frame = Instant::now();
+            let mut i = 0;
+            while if steps == 0 { frame.elapsed().as_secs_f32() < TURBO_MAX_FRAME } else { i < steps } {
+                if run.advance(&mut sim, dt) { clock_save = Some(Instant::now()); }
+                i += 1;
+            }
+        }
+
+        // ----------------- DRAWING
+
+        // clear background
+        clear_background(GRAY);
+
+        draw_track(&sim.track);
+        if show_deaths { draw_terminations(&sim); }
+
+        // don't print all entities, for performance reasons
+        if steps != 0 {
+            let colors = sim.neat.species_table.keys().cloned().collect::<Vec<usize>>();
+            sim.cars.iter().zip(sim.neat.agents.iter()).filter(|(c,_)| c.alive)
+                .for_each(|(c,n)| draw_car(c, contrasting_color(&colors, n.species)) );
+        }
+
+        //// print species leaders
+        //let colors = neat.species_table.keys().cloned().collect::<Vec<usize>>();
+        //colors.iter().for_each(|s| {
+        //    let best = neat.agents.iter().enumerate().filter(|(_,a)| a.species == *s)
+        //        .map(|(i,a)| (i, a.fitness) ).max_by(|a,b| a.partial_cmp(b).unwrap() ).unwrap();
+        //    let c = &cars[best.0];
+        //
+        //    for i in 0..c.points.len() {
+        //        draw_line(
+        //            c.points[i].x, c.points[i].y, 
+        //            c.points[(i+1)%c.points.len()].x, c.points[(i+1)%c.points.len()].y, 
+        //            4.0, 
+        //            contrasting_color(&colors, *s)
+        //        );
+        //    }
+        //} );
+
+        //draw_nn(&entities[best]);
+        
+        // fps
+        fta.add_frame_time(get_fps() as f32);
+        draw_text(&("FPS: ".to_owned() + &(fta.smooth_frame_time()).to_string()), WINDOW_SIZE.0 as f32 - 110.0, 20.0, 30.0, DARKGRAY);
+        // gen number
+        draw_text(&("GEN: ".to_owned() + &(sim.generation).to_string()), 10.0, WINDOW_SIZE.1 as f32 - 10.0, 50.0, DARKGRAY);
+        // time
+        let tracks = sim.config.population.tracks;
+        let track = if tracks > 1 { format!(" | Track: {}/{}", sim.episode + 1, tracks) } else { String::new() };
+        draw_text(&("Time: ".to_owned() + &(sim.elapsed() as u64).to_string() + " | Tick: " + &sim.tick.to_string() + &track), 10.0, WINDOW_SIZE.1 as f32 - 50.0, 30.0, DARKGRAY);
+        // simulation speed
+        let speed = if steps == 0 { "MAX".to_string() } else { "x".to_owned() + &steps.to_string() };
+        let speed = if slow == 0 { speed } else { "1/".to_owned() + &SLOW_SPEEDS[slow].to_string() };
+        draw_text(&("SPEED: ".to_owned() + &speed + if paused { " PAUSED" } else { "" }), 10.0, 30.0, 30.0, DARKGRAY);
+        // last validation
+        if let Some(v) = &run.last_validation { draw_text(&("VAL ".to_owned() + &validation_text(v)), 10.0, 60.0, 30.0, DARKGRAY); }
+        // alive number
+        draw_text(&("SUM: ".to_owned() + &(sim.alive_sum).to_string()), WINDOW_SIZE.0 as f32 - 200.0, WINDOW_SIZE.1 as f32 - 10.0, 50.0, DARKGRAY);
+        draw_text(leader_text(&sim), WINDOW_SIZE.0 as f32 - 520.0, WINDOW_SIZE.1 as f32 - 60.0, 30.0, DARKGRAY);
+
+        // Show screens
+        //if clock_read.elapsed().as_secs_f32() > TEXT_COOLDOWN {draw_cooldown(clock, &("GENERATION: ".to_owned() + &generation.to_string()), 100.0);}
+        if let Some(c) = clock_save { draw_cooldown(c, &("SAVED GEN: ".to_owned() + &sim.generation.to_string()), 200.0); }
+        if let Some(c) = clock_read { draw_cooldown(c, &("LOADED GEN: ".to_owned() + &sim.generation.to_string()), 200.0); }
+        if let Some(c) = clock_track { draw_cooldown(c, &("SAVED TRACK: ".to_owned() + &sim.track.info.seed.to_string()), 100.0); }
+        // std::thread::sleep(std::time::Duration::from_millis(4));
+        next_frame().await;
+    }
+}
@@ -1,10 +1,35 @@
-use crate::global::Point;
+//! Vehicle physics.
+
+use crate::geometry::Point;
 use std::f32::consts::PI;
 use serde::{Serialize, Deserialize};
 
 
+/// Why car's episode ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    /// Hit inner boundary.
+    InnerWall,
+    /// Hit outer boundary.
+    OuterWall,
+    /// Too slow, or out of gate time budget.
+    Stall,
+    /// Still driving when episode's time ran out.
+    Timeout,
+    /// Drove the wrong way for too long, see `population.wrong_way`.
+    WrongWay,
+    /// Turned too fast for too long, see `population.spin_limit`.
+    SpinOut,
+    /// Drove all laps of the race.
+    Finished,
+}
+
+/// Rigid polygon moving with linear and angular velocity, slowed down by friction.
+/// Angle 0 points the car along +y axis.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Car {
+    /// Car's polygon in track coordinates, refreshed by `update`.
     pub points: Vec<Point>,
     points_relative: Vec<Point>,
     position: Point,
@@ -14,23 +39,51 @@ pub struct Car {
     velocity_ang: f32,
     mass: f32,
     friction: f32,
-    pub distance: isize,
+    /// Position along track centreline, arc length from its start.
+    pub arc: f32,
+    /// Signed arc length travelled along centreline in race direction, negative when driving the wrong way.
+    pub progress: f32,
+    /// Highest `progress` reached.
+    pub furthest: f32,
+    /// Went back from `furthest` more than allowed.
+    pub wrong_way: bool,
+    /// Accumulated forward speed.
     pub agility: f32,
-    pub alive: bool
+    /// Still driving, false once it has a `termination`.
+    pub alive: bool,
+    /// Simulated seconds it was moving.
+    pub time: f32,
+    /// Why and where (car's position, it doesn't move after) its episode ended.
+    pub termination: Option<Termination>,
+    /// Seconds it has been spinning faster than allowed.
+    pub spin: f32,
+    /// Simulated time every finished lap took.
+    pub lap_times: Vec<f32>,
+    /// Simulated time current lap started at.
+    pub lap_start: f32,
+    /// Drove all laps of the race, stops racing after that.
+    pub finished: bool,
+    /// Checkpoint gates passed in order, over all laps.
+    pub gates: usize,
+    /// Simulated time its budget runs out at, when gates are on.
+    pub deadline: f32,
 }
 
 impl Car {
+    /// `points_relative` is car's shape around its center.
     pub fn new(points_relative: Vec<Point>, position: Point, angle: f32, mass: f32, friction: f32) -> Self { 
         Self { 
             points: points_relative.clone(), points_relative, 
             position: position.clone(), position_last: position, velocity: Point::new(0.0, 0.0), 
             angle, velocity_ang: 0.0, 
-            mass, friction, distance: 0, agility: 100., alive: true
+            mass, friction, arc: 0.0, progress: 0.0, furthest: 0.0, wrong_way: false, agility: 100., alive: true, time: 0.0, termination: None, spin: 0.0, lap_times: vec![], lap_start: 0.0, finished: false, gates: 0, deadline: f32::MAX
         } 
     }
 
+    /// Applies friction and moves car by its velocities.
     pub fn update(&mut self, dt: f32) {
         self.position_last = self.position.clone();
+        self.time += dt;
 
         self.velocity.x += self.velocity.x * -self.friction / self.mass * dt;
         self.velocity.y += self.velocity.y * -self.friction / self.mass * dt;
@@ -46,16 +99,39 @@ impl Car {
         });
     }
 
+    /// Accelerates in direction car is facing.
     pub fn acc_forward(&mut self, acc: f32, dt: f32) {
         self.velocity.x += acc * (self.angle+PI/2.0).cos() / self.mass * dt;
         self.velocity.y += acc * (self.angle+PI/2.0).sin() / self.mass * dt;
     }
 
+    /// Accelerates rotation.
     pub fn acc_ang(&mut self, acc: f32, dt: f32) {
         self.velocity_ang += acc / self.mass * dt;
     }
 
-    pub fn _reset(&mut self, pos: Point, angle: f32) {
+    /// Whole laps driven in race direction.
+    pub fn laps(&self, lap_length: f32) -> usize {
+        (self.progress.max(0.0) / lap_length.max(f32::EPSILON)) as usize
+    }
+
+    /// Part of current lap driven, 0..1.
+    pub fn lap_fraction(&self, lap_length: f32) -> f32 {
+        (self.progress.max(0.0) / lap_length.max(f32::EPSILON)).fract()
+    }
+
+    /// Hit a wall.
+    pub fn crashed(&self) -> bool {
+        matches!(self.termination, Some(Termination::InnerWall | Termination::OuterWall))
+    }
+
+    /// Fastest finished lap.
+    pub fn best_lap(&self) -> Option<f32> {
+        self.lap_times.iter().cloned().reduce(f32::min)
+    }
+
+    /// Stops the car at `pos` facing `angle`, lap and progress state is kept.
+    pub fn reset(&mut self, pos: Point, angle: f32) {
         self.position = pos.clone();
         self.position_last = pos;
         self.angle = angle;
@@ -63,21 +139,26 @@ impl Car {
         self.velocity = Point::new(0.0, 0.0);
     }
 
+    /// Heading in radians, 0 is +y.
     pub fn get_angle(&self) -> &f32 {
         &self.angle
     }
 
+    /// Centre of the car.
     pub fn get_position(&self) -> &Point {
         &self.position
     }
+    /// Position before last `update`.
     pub fn get_position_last(&self) -> &Point {
         &self.position_last
     }
 
+    /// Pixels per second.
     pub fn get_velocity(&self) -> &Point {
         &self.velocity
     }
 
+    /// Radians per second.
     pub fn get_velocity_ang(&self) -> &f32 {
         &self.velocity_ang
     }
@@ -87,62 +168,3 @@ impl Car {
     //}
 
 }
-
-
-fn raycast(point: &Point, angle: f32, polygon: &Vec<Point>) -> Option<Point> {
-    let mut min_t = std::f32::MAX;
-    let mut intersection: Option<Point> = None;
-
-    let x = point.x;
-    let y = point.y;
-    let dx = angle.cos();
-    let dy = angle.sin();
-
-    for i in 0..polygon.len() {
-        let j = (i + 1) % polygon.len();
-        let ax = polygon[i].x;
-        let ay = polygon[i].y;
-        let bx = polygon[j].x;
-        let by = polygon[j].y;
-
-        let t = ((ay - y) * (bx - ax) - (ax - x) * (by - ay)) / (dy * (bx - ax) - dx * (by - ay));
-
-        if t >= 0.0 && (dx * t + x) >= ax.min(bx) && (dx * t + x) <= ax.max(bx) && (dy * t + y) >= ay.min(by) && (dy * t + y) <= ay.max(by) && t < min_t {
-            min_t = t;
-            intersection = Some(Point::new(dx * t + x, dy * t + y));
-        }
-    }
-
-    intersection
-}
-
-pub fn raywrap(point: &Point, angle: f32, amount: usize, track: &Vec<Point>, track2: &Vec<Point>) -> (Vec<f32>, Vec<Point>) {
-    let mut dsts: Vec<f32> = vec![];
-    let mut pp: Vec<Point> = vec![];
-
-    for i in 0..amount {
-        
-        let ray1 = raycast(point, (2.0*PI/amount as f32)*i as f32+PI/2.0+angle, track);
-        let ray2 = raycast(point, (2.0*PI/amount as f32)*i as f32+PI/2.0+angle, track2);
-
-        if let (Some(ray1), Some(ray2)) = (ray1.clone(), ray2.clone()) {
-            let d1 = ((ray1.x - point.x).powi(2)+(ray1.y - point.y).powi(2)).sqrt();
-            let d2 = ((ray2.x - point.x).powi(2)+(ray2.y - point.y).powi(2)).sqrt();
-            if d1 < d2 {dsts.push(d1);pp.push(ray1);}
-            else {dsts.push(d2);pp.push(ray2);}
-        }
-        else if let Some(ray1) = ray1 {
-            let d = ((ray1.x - point.x).powi(2)+(ray1.y - point.y).powi(2)).sqrt();
-            dsts.push(d);
-            pp.push(ray1);
-        }
-        else if let Some(ray2) = ray2 {
-            let d = ((ray2.x - point.x).powi(2)+(ray2.y - point.y).powi(2)).sqrt();
-            dsts.push(d);
-            pp.push(ray2);
-        }
-        else {dsts.push(f32::MAX);pp.push(Point::new(-100.0, -100.0));}
-
-    }
-    (dsts, pp)
-}
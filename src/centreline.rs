@@ -0,0 +1,134 @@
+//! Arc-length position along the track.
+
+use serde::{Serialize, Deserialize};
+
+use crate::geometry::{distance, get_angle, project_on_segment, Point};
+
+
+/// Way cars race around the track, relative to centreline.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Along the centreline, the way inner boundary goes.
+    Forward,
+    /// Against it.
+    Reverse,
+}
+
+impl Direction {
+    /// 1 forward, -1 reverse, arc length changes by that much per pixel driven.
+    pub fn sign(&self) -> f32 {
+        match self { Direction::Forward => 1.0, Direction::Reverse => -1.0 }
+    }
+}
+
+/// Closed line through the middle of the track, parameterised by arc length.
+/// Direction follows the inner boundary, positive progress means driving that way.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Centreline {
+    /// One point per inner boundary vertex.
+    pub points: Vec<Point>,
+    /// Arc length at every point, first one is 0.
+    arc: Vec<f32>,
+    /// Length of one lap.
+    pub length: f32,
+}
+
+impl Centreline {
+    /// Midpoints between every inner vertex and the closest point of outer boundary.
+    pub fn new(inner: &[Point], outer: &[Point]) -> Self {
+        let points: Vec<Point> = inner.iter().map(|p| {
+            let closest = (0..outer.len())
+                .map(|i| project_on_segment(p, &outer[i], &outer[(i+1) % outer.len()]).0 )
+                .min_by(|a, b| distance(p, a).total_cmp(&distance(p, b)) )
+                .unwrap_or_else(|| p.clone());
+            Point::new((p.x + closest.x) / 2.0, (p.y + closest.y) / 2.0)
+        }).collect();
+
+        let mut arc = vec![0.0];
+        for i in 1..=points.len() {
+            arc.push(arc[i-1] + distance(&points[i-1], &points[i % points.len()]));
+        }
+        let length = arc.pop().unwrap_or(0.0);
+        Self { points, arc, length }
+    }
+
+    /// Arc length of the centreline point closest to `p`, in 0..length.
+    pub fn project(&self, p: &Point) -> f32 {
+        let n = self.points.len();
+        let mut best = (f32::MAX, 0.0);
+        for i in 0..n {
+            let (a, b) = (&self.points[i], &self.points[(i+1) % n]);
+            let (q, t) = project_on_segment(p, a, b);
+            let d = distance(p, &q);
+            if d < best.0 { best = (d, self.arc[i] + t * distance(a, b)); }
+        }
+        best.1 % self.length.max(f32::EPSILON)
+    }
+
+    /// Point at `arc` length and direction of centreline there.
+    pub fn point_at(&self, arc: f32) -> (Point, f32) {
+        let n = self.points.len();
+        let arc = arc.rem_euclid(self.length.max(f32::EPSILON));
+        let i = self.arc.partition_point(|a| *a <= arc).saturating_sub(1);
+        let (a, b) = (&self.points[i], &self.points[(i+1) % n]);
+        let t = (arc - self.arc[i]) / distance(a, b).max(f32::EPSILON);
+        (Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t), get_angle(a, b))
+    }
+
+    /// Segment across the track at `arc`, `width` long.
+    pub fn gate_at(&self, arc: f32, width: f32) -> (Point, Point) {
+        let (p, angle) = self.point_at(arc);
+        let (dx, dy) = (angle.sin() * width / 2.0, -angle.cos() * width / 2.0);
+        (Point::new(p.x - dx, p.y - dy), Point::new(p.x + dx, p.y + dy))
+    }
+
+    /// Signed shortest way from `from` to `to` arc length, wrapping around start.
+    pub fn delta(&self, from: f32, to: f32) -> f32 {
+        let d = to - from;
+        if d > self.length / 2.0 { d - self.length }
+        else if d < -self.length / 2.0 { d + self.length }
+        else { d }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // boundaries on top of each other make the centreline the square itself, 400 long, starting at (0, 0)
+    fn square() -> Centreline {
+        let s = vec![Point::new(0., 0.), Point::new(100., 0.), Point::new(100., 100.), Point::new(0., 100.)];
+        Centreline::new(&s, &s)
+    }
+
+    #[test]
+    fn projection_wraps_at_start() {
+        let c = square();
+        assert!((c.length - 400.).abs() < 1e-3);
+        assert!((c.project(&Point::new(5., -1.)) - 5.).abs() < 1e-3);
+        // just before the start, on the closing edge
+        assert!((c.project(&Point::new(-1., 5.)) - 395.).abs() < 1e-3);
+        let at_start = c.project(&Point::new(0., 0.));
+        assert!((0.0..c.length).contains(&at_start));
+    }
+
+    #[test]
+    fn delta_takes_shorter_way_across_start() {
+        let c = square();
+        assert!((c.delta(395., 5.) - 10.).abs() < 1e-3);
+        assert!((c.delta(5., 395.) + 10.).abs() < 1e-3);
+        assert!((c.delta(10., 20.) - 10.).abs() < 1e-3);
+        assert!((c.delta(20., 10.) + 10.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn point_at_wraps_both_ways() {
+        let c = square();
+        let (p, _) = c.point_at(-5.);
+        assert!(distance(&p, &Point::new(0., 5.)) < 1e-3);
+        let (p, _) = c.point_at(405.);
+        assert!(distance(&p, &Point::new(5., 0.)) < 1e-3);
+    }
+}
@@ -0,0 +1,102 @@
+//! Saving and loading of training state.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use rand_chacha::ChaCha8Rng;
+use rusty_neat::{NodeKey, NN};
+use serde::{Serialize, Deserialize};
+
+use crate::geometry::Point;
+use crate::config::Config;
+use crate::hall_of_fame::HallEntry;
+
+
+/// Bumped every time layout of `Checkpoint` changes.
+pub const CHECKPOINT_VERSION: u32 = 1;
+const CHECKPOINT_EXT: &str = "ckpt";
+
+/// Everything needed to continue training from the start of a generation.
+/// Cars aren't stored, loaded generation starts over on the saved track.
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Config the run was started with.
+    pub config: Config,
+    /// Generation to start.
+    pub generation: usize,
+    /// Master seed of the run.
+    pub seed: u64,
+    /// Seed of the track generation starts on.
+    pub track_seed: u64,
+    /// Boundaries of the track, the rest is derived from them again on load.
+    pub inner: Vec<Point>,
+    /// Outer boundary, see `inner`.
+    pub outer: Vec<Point>,
+    /// Simulation rng, as it was at the end of the previous generation.
+    pub rng: ChaCha8Rng,
+    // NeatIntermittent isn't serializable, so it's stored field by field.
+    // Species table is rebuilt with speciate() on load.
+    /// Population, with fitness of the previous generation.
+    pub agents: Vec<NN>,
+    /// Population size.
+    pub size: usize,
+    /// Next free innovation number.
+    pub innov_id: usize,
+    /// Innovation number of every connection (from, to, recurrent) seen so far.
+    pub innov_table: Vec<((NodeKey, NodeKey, bool), usize)>,
+    /// Compatibility distance below which networks share species, adjusted every generation.
+    pub species_threshold: f32,
+    /// Number of species `species_threshold` aims for.
+    pub species_amount: usize,
+    /// Best genomes so far.
+    pub hall_of_fame: Vec<HallEntry>,
+}
+
+impl Checkpoint {
+    /// File starts with version number, followed by bincode encoded checkpoint.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut file, &CHECKPOINT_VERSION).map_err(io::Error::other)?;
+        bincode::serialize_into(&mut file, self).map_err(io::Error::other)
+    }
+
+    /// Reads checkpoint written by `save`, fails on other versions.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let version: u32 = bincode::deserialize_from(&mut file).map_err(io::Error::other)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("checkpoint version {} is not supported (expected {})", version, CHECKPOINT_VERSION)));
+        }
+        bincode::deserialize_from(file).map_err(io::Error::other)
+    }
+
+    /// Saves into `dir` as `gen_XXXXXX.ckpt`, leaving only `keep` newest checkpoints there.
+    pub fn save_rotating(&self, dir: &Path, keep: usize) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("gen_{:06}.{}", self.generation, CHECKPOINT_EXT));
+        self.save(&path)?;
+
+        let all = list(dir)?;
+        for old in all.iter().take(all.len().saturating_sub(keep)) {
+            fs::remove_file(old)?;
+        }
+        Ok(path)
+    }
+}
+
+/// Newest checkpoint in `dir`, if there is any.
+pub fn latest(dir: &Path) -> Option<PathBuf> {
+    list(dir).ok()?.pop()
+}
+
+// names are zero padded, so sorting by name sorts by generation
+fn list(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()) )
+        .filter(|p| p.extension().is_some_and(|e| e == CHECKPOINT_EXT) )
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
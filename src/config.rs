@@ -0,0 +1,462 @@
+//! Runtime settings.
+
+use std::{fmt, fs, io};
+use std::path::Path;
+
+use rusty_neat::ActFunc;
+use serde::{Serialize, Deserialize};
+
+use crate::geometry::{mix_seed, Point};
+use crate::stats::Aggregate;
+use crate::fitness::{FitnessKind, Shaping};
+use crate::centreline::Direction;
+use crate::track::Generator;
+
+
+// salt of direction pick, out of the way of generation numbers
+const DIRECTION_SALT: u64 = u64::MAX - 1;
+
+/// Runtime settings of a training run, read from TOML.
+/// Missing keys fall back to defaults, unknown keys are rejected.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Master seed, random if not set.
+    pub seed: Option<u64>,
+    /// `[track]` table.
+    pub track: TrackConfig,
+    /// `[population]` table.
+    pub population: PopulationConfig,
+    /// `[network]` table.
+    pub network: NetworkConfig,
+    /// `[car]` table.
+    pub car: CarConfig,
+    /// `[fitness]` table.
+    pub fitness: FitnessConfig,
+    /// `[gates]` table.
+    pub gates: GateConfig,
+    /// `[hall_of_fame]` table.
+    pub hall_of_fame: HallOfFameConfig,
+    /// `[validation]` table.
+    pub validation: ValidationConfig,
+    /// `[run]` table.
+    pub run: RunConfig,
+}
+
+/// Track generation, or the track file used instead.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackConfig {
+    /// Amount of random points hull is built from, kind a smoothness of track.
+    pub grain: usize,
+    /// Amount of chaikin's corner cutting iterations.
+    pub res: usize,
+    /// Track width generators aim for.
+    pub width: f32,
+    /// Narrowest corridor generated tracks can have, should fit a car.
+    pub min_width: f32,
+    /// Track is generated inside (0,0)..size area, minus width margin.
+    pub size: (f32, f32),
+    /// Shape of generated tracks.
+    pub generator: Generator,
+    /// How far non-convex generators go from a smooth blob, 0..1.
+    pub roughness: f32,
+    /// Way cars race around tracks.
+    pub direction: RaceDirection,
+    /// Track file every generation trains on instead of generated ones, raced the way its seed picks.
+    pub file: Option<String>,
+}
+
+/// Race direction of tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RaceDirection {
+    /// Always along the centreline.
+    Forward,
+    /// Always against it.
+    Reverse,
+    /// Picked from track seed, so both directions get trained.
+    Both,
+}
+
+impl RaceDirection {
+    /// Direction of track with `track_seed`.
+    pub fn pick(&self, track_seed: u64) -> Direction {
+        match self {
+            RaceDirection::Forward => Direction::Forward,
+            RaceDirection::Reverse => Direction::Reverse,
+            RaceDirection::Both => if mix_seed(track_seed, DIRECTION_SALT).is_multiple_of(2) { Direction::Forward } else { Direction::Reverse },
+        }
+    }
+}
+
+/// What happens to cars driving the wrong way.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrongWay {
+    /// Nothing, it's only counted.
+    Ignore,
+    /// Fitness is multiplied by `wrong_way_penalty`.
+    Penalty,
+    /// Car is killed.
+    Kill,
+}
+
+/// Population size and how its episodes are run and ended.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PopulationConfig {
+    /// Amount of cars in one generation.
+    pub entities: usize,
+    /// Desired amount of species.
+    pub species: usize,
+    /// Max initial time for each generation, in seconds.
+    pub gen_len: f32,
+    /// Generation time limit grows by this much every generation.
+    pub gen_len_growth: f32,
+    /// Cars whose progress falls behind this speed, in pixels per second, are killed, when there are no gates.
+    pub min_speed: f32,
+    /// What happens to cars driving the wrong way.
+    pub wrong_way: WrongWay,
+    /// How far cars can go back from their furthest progress before they count as driving the wrong way.
+    pub wrong_way_limit: f32,
+    /// Fitness multiplier of `wrong_way = "penalty"`, 0..=1.
+    pub wrong_way_penalty: f32,
+    /// Cars turning faster than this, in radians per second, for `spin_time` seconds spin out, 0 (default) disables it.
+    pub spin_limit: f32,
+    /// Seconds of too fast turning a car survives.
+    pub spin_time: f32,
+    /// Cars that drive this many laps finish the race, 0 means no limit.
+    pub race_laps: usize,
+    /// Use fixed `dt` step instead of frame time.
+    pub static_dt: bool,
+    /// Fixed step length, in seconds.
+    pub dt: f32,
+    /// Every agent drives this many tracks per generation, one after another.
+    pub tracks: usize,
+    /// How fitness from those tracks is combined.
+    pub aggregate: Aggregate,
+    /// Fraction of best and worst tracks ignored by `trimmed_mean`.
+    pub trim: f32,
+}
+
+/// Shape of networks of the first generation and how they mutate.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Amount of distance rays on every car ( 16 best ).
+    pub rays: usize,
+    /// Mutations can add recurrent connections.
+    pub recurrence: bool,
+    /// How much new data influences recurrent node's memory.
+    pub recurrence_weight: f32,
+    /// Activation of input and output nodes.
+    pub activation_io: ActFunc,
+    /// Activations that can appear during mutations.
+    pub activations: Vec<ActFunc>,
+}
+
+/// Car physics.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CarConfig {
+    /// Divides every force.
+    pub mass: f32,
+    /// Slows the car down in proportion to its velocity.
+    pub friction: f32,
+    /// Polygon relative to car's center.
+    pub shape: Vec<(f32, f32)>,
+}
+
+/// Checkpoint gates evenly spread along the track, each one passed in order extends car's time budget.
+/// They replace `population.min_speed` kill.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GateConfig {
+    /// Gates per lap, start/finish line being one of them, 0 disables them.
+    pub count: usize,
+    /// Time budget at start, in seconds.
+    pub initial_time: f32,
+    /// Seconds added to budget by every gate.
+    pub extension: f32,
+}
+
+/// What cars are rewarded for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FitnessConfig {
+    /// Raw fitness of one track.
+    pub function: FitnessKind,
+    /// Parts of `weighted` function.
+    pub weights: Vec<(FitnessKind, f32)>,
+    /// Applied right before breeding.
+    pub shaping: Shaping,
+}
+
+/// Best genomes kept across generations.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HallOfFameConfig {
+    /// Amount of best genomes kept across generations, 0 disables it.
+    pub size: usize,
+    /// Amount of them copied unchanged into every new generation.
+    pub reinject: usize,
+}
+
+/// Held-out tracks the champion is tested on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ValidationConfig {
+    /// Champion drives validation tracks every this many generations, 0 disables it.
+    pub every: usize,
+    /// Seeds of generated validation tracks, never used for training.
+    pub seeds: Vec<u64>,
+    /// Track files validated on too.
+    pub files: Vec<String>,
+    /// Time limit on every validation track, in seconds.
+    pub time_limit: f32,
+}
+
+/// Where and how often runs are saved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    /// Every run gets its own subdirectory here.
+    pub output_dir: String,
+    /// Autosave period in generations.
+    pub checkpoint_every: usize,
+    /// Older checkpoints are removed.
+    pub checkpoint_keep: usize,
+}
+
+impl Default for TrackConfig {
+    fn default() -> Self {
+        Self { grain: 24, res: 3, width: 110.0, min_width: 40.0, size: (1920.0, 1080.0), generator: Generator::ConvexHull, roughness: 0.4, direction: RaceDirection::Forward, file: None }
+    }
+}
+
+impl Default for PopulationConfig {
+    fn default() -> Self {
+        Self { entities: 2000, species: 7, gen_len: 20.0, gen_len_growth: 3.0, min_speed: 120.0,
+            wrong_way: WrongWay::Kill, wrong_way_limit: 200.0, wrong_way_penalty: 0.1,
+            spin_limit: 0.0, spin_time: 1.5, race_laps: 0, static_dt: true, dt: 0.03333,
+            tracks: 1, aggregate: Aggregate::Mean, trim: 0.25 }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            rays: 8,
+            recurrence: true,
+            recurrence_weight: 0.75,
+            activation_io: ActFunc::SigmoidBipolar,
+            activations: vec![ActFunc::SigmoidBipolar, ActFunc::SELU, ActFunc::HyperbolicTangent],
+        }
+    }
+}
+
+impl Default for CarConfig {
+    fn default() -> Self {
+        Self { mass: 1.0, friction: 0.9, shape: vec![(-13.0, -20.0), (13.0, -20.0), (13.0, 20.0), (-13.0, 20.0)] }
+    }
+}
+
+impl Default for GateConfig {
+    fn default() -> Self {
+        Self { count: 20, initial_time: 4.0, extension: 2.0 }
+    }
+}
+
+impl Default for FitnessConfig {
+    fn default() -> Self {
+        Self {
+            function: FitnessKind::ForwardSpeed,
+            weights: vec![(FitnessKind::ForwardSpeed, 1.0), (FitnessKind::Progress, 10.0)],
+            shaping: Shaping::FourthRoot,
+        }
+    }
+}
+
+impl Default for HallOfFameConfig {
+    fn default() -> Self {
+        Self { size: 10, reinject: 0 }
+    }
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self { every: 10, seeds: vec![1, 2, 3, 4, 5], files: vec![], time_limit: 60.0 }
+    }
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self { output_dir: "runs".to_string(), checkpoint_every: 10, checkpoint_keep: 5 }
+    }
+}
+
+/// Why config couldn't be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// File can't be read.
+    Io(io::Error),
+    /// Not valid TOML, or it doesn't fit `Config`.
+    Parse(String),
+    /// Value out of its range.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "can't read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "can't parse config: {}", e),
+            ConfigError::Invalid(e) => write!(f, "invalid config: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Reads TOML file (defaults if `None`), applies `key.path=value` overrides and validates result.
+    pub fn load(path: Option<&Path>, overrides: &[String]) -> Result<Self, ConfigError> {
+        let mut table: toml::Table = match path {
+            Some(p) => toml::from_str(&fs::read_to_string(p).map_err(ConfigError::Io)?)
+                .map_err(|e| ConfigError::Parse(format!("{}: {}", p.display(), e)))?,
+            None => toml::Table::new(),
+        };
+        for o in overrides { set_override(&mut table, o)?; }
+
+        let config: Config = toml::Value::Table(table).try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Writes it as TOML, loading it gives the same config.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, toml::to_string(self).map_err(io::Error::other)?)
+    }
+
+    /// Checks every value is in its range, and `track.file` exists.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let check = |ok: bool, msg: &str| if ok { Ok(()) } else { Err(ConfigError::Invalid(msg.to_string())) };
+
+        // TOML integers are signed
+        check(self.seed.is_none_or(|s| s <= i64::MAX as u64), "seed must fit in i64")?;
+        check(self.track.grain >= 3, "track.grain must be at least 3")?;
+        // every pass doubles the points
+        check(self.track.res <= 6, "track.res can't be more than 6")?;
+        check((0.0..1.0).contains(&self.track.roughness), "track.roughness must be in 0..1")?;
+        check(self.track.width > 0.0, "track.width must be positive")?;
+        check(self.track.min_width > 0.0 && self.track.min_width <= self.track.width, "track.min_width must be in 0..=track.width")?;
+        check(self.track.file.as_ref().is_none_or(|f| Path::new(f).is_file()), "track.file doesn't exist")?;
+        check(self.track.size.0 > self.track.width * 2.0 && self.track.size.1 > self.track.width * 2.0,
+            "track.size must be larger than two track widths")?;
+        check(self.population.entities > 0, "population.entities must be positive")?;
+        check(self.population.species > 0, "population.species must be positive")?;
+        check(self.population.gen_len > 0.0, "population.gen_len must be positive")?;
+        check(self.population.gen_len_growth >= 0.0, "population.gen_len_growth can't be negative")?;
+        check(self.population.min_speed >= 0.0, "population.min_speed can't be negative")?;
+        check(self.population.wrong_way_limit >= 0.0, "population.wrong_way_limit can't be negative")?;
+        check((0.0..=1.0).contains(&self.population.wrong_way_penalty), "population.wrong_way_penalty must be in 0..=1")?;
+        check(self.population.spin_limit >= 0.0 && self.population.spin_time >= 0.0, "population.spin_limit and spin_time can't be negative")?;
+        check(self.population.dt > 0.0, "population.dt must be positive")?;
+        check(self.population.tracks > 0, "population.tracks must be positive")?;
+        check((0.0..0.5).contains(&self.population.trim), "population.trim must be in 0..0.5")?;
+        check(self.network.rays > 0, "network.rays must be positive")?;
+        check((0.0..=1.0).contains(&self.network.recurrence_weight), "network.recurrence_weight must be in 0..=1")?;
+        check(!self.network.activations.is_empty(), "network.activations can't be empty")?;
+        check(self.car.mass > 0.0, "car.mass must be positive")?;
+        check(self.car.friction >= 0.0, "car.friction can't be negative")?;
+        check(self.car.shape.len() >= 3, "car.shape needs at least 3 points")?;
+        check(self.fitness.weights.iter().all(|(k, _)| *k != FitnessKind::Weighted), "fitness.weights can't contain weighted")?;
+        check(self.gates.initial_time > 0.0, "gates.initial_time must be positive")?;
+        check(self.gates.extension >= 0.0, "gates.extension can't be negative")?;
+        check(self.hall_of_fame.reinject <= self.hall_of_fame.size, "hall_of_fame.reinject can't exceed hall_of_fame.size")?;
+        check(self.hall_of_fame.reinject < self.population.entities, "hall_of_fame.reinject must be below population.entities")?;
+        check(self.validation.seeds.iter().all(|s| *s <= i64::MAX as u64), "validation.seeds must fit in i64")?;
+        check(self.validation.time_limit > 0.0, "validation.time_limit must be positive")?;
+        check(self.run.checkpoint_every > 0, "run.checkpoint_every must be positive")?;
+        check(self.run.checkpoint_keep > 0, "run.checkpoint_keep must be positive")?;
+        Ok(())
+    }
+
+    /// `car.shape` as points.
+    pub fn car_shape(&self) -> Vec<Point> {
+        self.car.shape.iter().map(|(x, y)| Point::new(*x, *y) ).collect()
+    }
+}
+
+// "population.entities=500", value is parsed as TOML, or taken as plain string if that fails
+fn set_override(table: &mut toml::Table, o: &str) -> Result<(), ConfigError> {
+    let (key, value) = o.split_once('=')
+        .ok_or_else(|| ConfigError::Parse(format!("override \"{}\" should look like key=value", o)))?;
+    let value = match toml::from_str::<toml::Table>(&format!("v = {}", value)) {
+        Ok(mut t) => t.remove("v").unwrap(),
+        Err(_) => toml::Value::String(value.to_string()),
+    };
+
+    let mut path: Vec<&str> = key.trim().split('.').collect();
+    let last = path.pop().unwrap();
+    let mut current = table;
+    for p in path {
+        current = current.entry(p).or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| ConfigError::Parse(format!("override \"{}\": {} is not a table", o, p)))?;
+    }
+    current.insert(last.to_string(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> toml::Table {
+        toml::Value::try_from(Config::default()).unwrap().as_table().unwrap().clone()
+    }
+
+    #[test]
+    fn overrides_are_parsed_as_toml_or_taken_as_strings() {
+        let mut t = table();
+        set_override(&mut t, "population.entities=500").unwrap();
+        set_override(&mut t, "track.width = 90.5").unwrap();
+        set_override(&mut t, "run.output_dir=out/x").unwrap();
+        set_override(&mut t, "track.generator=\"polar\"").unwrap();
+
+        let config: Config = toml::Value::Table(t).try_into().unwrap();
+        assert_eq!(config.population.entities, 500);
+        assert_eq!(config.track.width, 90.5);
+        assert_eq!(config.run.output_dir, "out/x");
+        assert_eq!(config.track.generator, Generator::Polar);
+    }
+
+    #[test]
+    fn bad_overrides_are_rejected() {
+        let mut t = table();
+        assert!(matches!(set_override(&mut t, "population.entities"), Err(ConfigError::Parse(_))));
+        match set_override(&mut t, "population.entities.x=1") {
+            Err(ConfigError::Parse(e)) => assert!(e.contains("entities is not a table")),
+            r => panic!("expected parse error, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn out_of_range_values_are_invalid() {
+        assert!(Config::default().validate().is_ok());
+
+        let invalid = |f: fn(&mut Config)| {
+            let mut config = Config::default();
+            f(&mut config);
+            matches!(config.validate(), Err(ConfigError::Invalid(_)))
+        };
+        assert!(invalid(|c| c.track.res = 30));
+        assert!(invalid(|c| c.track.grain = 2));
+        assert!(invalid(|c| c.population.entities = 0));
+        assert!(invalid(|c| c.track.min_width = c.track.width + 1.0));
+        assert!(invalid(|c| c.hall_of_fame.reinject = c.hall_of_fame.size + 1));
+    }
+}
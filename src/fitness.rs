@@ -0,0 +1,142 @@
+//! What cars are rewarded for.
+
+use serde::{Serialize, Deserialize};
+
+use crate::car::Car;
+use crate::track::Track;
+
+
+/// Track facts fitness may depend on.
+pub struct FitnessContext<'a> {
+    /// Track the car drove, its centreline length is one lap of `Car::progress`.
+    pub track: &'a Track,
+}
+
+/// Raw fitness of a car, evaluated every step and after its episode.
+/// Has to be non-negative, shaping and breeding expect that.
+pub trait FitnessFunction: Send + Sync {
+    /// Fitness of `car` so far.
+    fn fitness(&self, car: &Car, ctx: &FitnessContext) -> f32;
+}
+
+/// Built-in fitness functions, selectable from config.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitnessKind {
+    /// Integral of forward speed.
+    ForwardSpeed,
+    /// Arc length travelled along centreline in race direction.
+    Progress,
+    /// Whole laps completed.
+    Laps,
+    /// Checkpoint gates passed.
+    Gates,
+    /// Faster best lap scores higher, cars without one get their fraction of lap.
+    LapTime,
+    /// Progress per second alive.
+    ProgressRate,
+    /// Sum of `fitness.weights`.
+    Weighted,
+}
+
+/// See `FitnessKind::ForwardSpeed`.
+pub struct ForwardSpeed;
+/// See `FitnessKind::Progress`.
+pub struct Progress;
+/// See `FitnessKind::Laps`.
+pub struct Laps;
+/// See `FitnessKind::Gates`.
+pub struct Gates;
+/// See `FitnessKind::LapTime`.
+pub struct LapTime;
+/// See `FitnessKind::ProgressRate`.
+pub struct ProgressRate;
+/// Weighted sum of other functions.
+pub struct Weighted(pub Vec<(f32, Box<dyn FitnessFunction>)>);
+
+impl FitnessFunction for ForwardSpeed {
+    fn fitness(&self, car: &Car, _: &FitnessContext) -> f32 {
+        car.agility.max(0.0)
+    }
+}
+
+impl FitnessFunction for Progress {
+    fn fitness(&self, car: &Car, _: &FitnessContext) -> f32 {
+        car.progress.max(0.0)
+    }
+}
+
+impl FitnessFunction for Laps {
+    fn fitness(&self, car: &Car, ctx: &FitnessContext) -> f32 {
+        car.laps(ctx.track.centreline.length) as f32
+    }
+}
+
+impl FitnessFunction for Gates {
+    fn fitness(&self, car: &Car, _: &FitnessContext) -> f32 {
+        car.gates as f32
+    }
+}
+
+impl FitnessFunction for LapTime {
+    fn fitness(&self, car: &Car, ctx: &FitnessContext) -> f32 {
+        match car.best_lap() {
+            // always above any unfinished lap
+            Some(t) => 1.0 + 100.0 / t.max(0.001),
+            None => car.lap_fraction(ctx.track.centreline.length),
+        }
+    }
+}
+
+impl FitnessFunction for ProgressRate {
+    fn fitness(&self, car: &Car, _: &FitnessContext) -> f32 {
+        if car.time <= 0.0 { 0.0 } else { car.progress.max(0.0) / car.time }
+    }
+}
+
+impl FitnessFunction for Weighted {
+    fn fitness(&self, car: &Car, ctx: &FitnessContext) -> f32 {
+        self.0.iter().map(|(w, f)| w * f.fitness(car, ctx) ).sum::<f32>().max(0.0)
+    }
+}
+
+impl FitnessKind {
+    /// `weights` are only used by `Weighted`.
+    pub fn build(&self, weights: &[(FitnessKind, f32)]) -> Box<dyn FitnessFunction> {
+        match self {
+            FitnessKind::ForwardSpeed => Box::new(ForwardSpeed),
+            FitnessKind::Progress => Box::new(Progress),
+            FitnessKind::Laps => Box::new(Laps),
+            FitnessKind::Gates => Box::new(Gates),
+            FitnessKind::LapTime => Box::new(LapTime),
+            FitnessKind::ProgressRate => Box::new(ProgressRate),
+            FitnessKind::Weighted => Box::new(Weighted(weights.iter().map(|(k, w)| (*w, k.build(&[])) ).collect())),
+        }
+    }
+}
+
+/// Transform of fitness right before breeding, flattens differences between agents.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shaping {
+    /// Fitness as it is.
+    None,
+    /// Square root.
+    Sqrt,
+    /// Square root of square root.
+    FourthRoot,
+    /// ln(1 + x)
+    Log,
+}
+
+impl Shaping {
+    /// Shaped fitness, `x` is non-negative.
+    pub fn apply(&self, x: f32) -> f32 {
+        match self {
+            Shaping::None => x,
+            Shaping::Sqrt => x.sqrt(),
+            Shaping::FourthRoot => x.sqrt().sqrt(),
+            Shaping::Log => x.ln_1p(),
+        }
+    }
+}
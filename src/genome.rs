@@ -0,0 +1,41 @@
+//! Single trained network on disk.
+
+use std::{fs, io};
+use std::path::Path;
+
+use rusty_neat::NN;
+use serde::{Serialize, Deserialize};
+
+
+/// Network together with where it came from, stored as TOML.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Genome {
+    /// Raw fitness it got, before shaping.
+    pub fitness: f32,
+    /// Generation it was saved in.
+    pub generation: usize,
+    /// Track it got that fitness on, the last one if generation had more.
+    pub track_seed: u64,
+    /// The network itself.
+    pub nn: NN,
+}
+
+impl Genome {
+    /// Writes it as TOML.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, toml::to_string(self).map_err(io::Error::other)?)
+    }
+
+    /// Plain `NN::save` files are accepted too, with fitness taken from network and zeroed rest.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let s = fs::read_to_string(path)?;
+        match toml::from_str::<Genome>(&s) {
+            Ok(g) => Ok(g),
+            Err(e) => match toml::from_str::<NN>(&s) {
+                Ok(nn) => Ok(Genome { fitness: nn.fitness, generation: 0, track_seed: 0, nn }),
+                // error of the format that was most likely meant
+                Err(_) => Err(io::Error::other(e)),
+            },
+        }
+    }
+}
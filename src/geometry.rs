@@ -0,0 +1,232 @@
+//! 2D points and polygon helpers.
+
+use std::f32::consts::PI;
+
+use serde::{Serialize, Deserialize};
+
+
+/// Point or vector in screen coordinates, y grows down.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Point {
+    /// Pixels from the left.
+    pub x: f32,
+    /// Pixels from the top.
+    pub y: f32
+} 
+    
+impl Point {
+    /// Point at `x`, `y`.
+    pub fn new(x: f32, y: f32) -> Self { Self { x, y } }
+    
+    /// Halfway between both points.
+    pub fn mid(&self, other: &Point) -> Point {
+        Point::new((self.x + other.x) / 2.0, (self.y + other.y) / 2.0)
+    }
+
+    /// Length as a vector.
+    pub fn length(&self) -> f32 {
+        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    }
+    /// Length of projection on direction car with angle `a` is facing.
+    pub fn length_project(&self, a: &f32) -> f32 {
+        self.x * (a+PI/2.0).cos() + self.y * (a+PI/2.0).sin()
+    }
+} 
+
+/// Even-odd rule.
+pub fn point_in_polygon(point: &Point, polygon: &[Point]) -> bool {
+    let mut intersections = 0;
+    for i in 0..polygon.len() {
+        let a = polygon[i].clone();
+        let b = polygon[(i + 1) % polygon.len()].clone();
+        if (a.y > point.y) != (b.y > point.y) &&
+            point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            intersections += 1;
+        }
+    }
+    intersections % 2 == 1
+}
+
+/// Euclidean distance.
+pub fn distance(p1: &Point, p2: &Point) -> f32 {
+    ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt()
+}
+
+/// Mean distance between every pair of points.
+pub fn average_distance(points: &[Point]) -> f32 {
+    let mut distance_sum = 0.0;
+    for i in 0..points.len() {
+        for j in (i + 1)..points.len() {
+            let dx = points[i].x - points[j].x;
+            let dy = points[i].y - points[j].y;
+            let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+            distance_sum += distance;
+        }
+    }
+    distance_sum / (points.len() * (points.len() - 1) / 2) as f32
+}
+
+/// Shoelace formula, positive for the orientation convex hull returns.
+pub fn signed_area(polygon: &[Point]) -> f32 {
+    (0..polygon.len()).map(|i| {
+        let (p, q) = (&polygon[i], &polygon[(i+1) % polygon.len()]);
+        p.x * q.y - q.x * p.y
+    }).sum::<f32>() / 2.0
+}
+
+/// Proper intersection of segments `a`-`b` and `c`-`d`, touching ends don't count.
+pub fn segment_intersection(a: &Point, b: &Point, c: &Point, d: &Point) -> Option<Point> {
+    let cross = |o: &Point, p: &Point, q: &Point| (p.x - o.x) * (q.y - o.y) - (p.y - o.y) * (q.x - o.x);
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        let t = d1 / (d1 - d2);
+        Some(Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t))
+    } else { None }
+}
+
+/// Segments `a`-`b` and `c`-`d` cross, same rules as `segment_intersection`.
+pub fn segments_intersect(a: &Point, b: &Point, c: &Point, d: &Point) -> bool {
+    segment_intersection(a, b, c, d).is_some()
+}
+
+/// Cuts off loops spanning at most `max_span` edges, made by offsetting sharp bends,
+/// crossing point replaces vertices inside the loop.
+pub fn remove_loops(polygon: &[Point], max_span: usize) -> Vec<Point> {
+    let mut p = polygon.to_vec();
+    'outer: loop {
+        let n = p.len();
+        for i in 0..n {
+            for span in 2..=max_span.min(n.saturating_sub(2)) {
+                let j = (i + span) % n;
+                if let Some(x) = segment_intersection(&p[i], &p[(i+1) % n], &p[j], &p[(j+1) % n]) {
+                    // keep j+1 ..= i, around the end if needed
+                    let mut kept: Vec<Point> = (0..n - span).map(|k| p[(j + 1 + k) % n].clone() ).collect();
+                    kept.push(x);
+                    p = kept;
+                    continue 'outer;
+                }
+            }
+        }
+        return p;
+    }
+}
+
+/// Distance from `p` to the closest edge of closed polygon.
+pub fn distance_to_polygon(p: &Point, polygon: &[Point]) -> f32 {
+    (0..polygon.len()).map(|i| distance(p, &project_on_segment(p, &polygon[i], &polygon[(i+1) % polygon.len()]).0) )
+        .fold(f32::MAX, f32::min)
+}
+
+/// Closed polygon without crossing edges.
+pub fn is_simple(polygon: &[Point]) -> bool {
+    let n = polygon.len();
+    for i in 0..n {
+        // neighbouring edges share a vertex, so start two edges further
+        for j in i+2..n {
+            if i == 0 && j == n-1 { continue; }
+            if segments_intersect(&polygon[i], &polygon[(i+1) % n], &polygon[j], &polygon[(j+1) % n]) { return false; }
+        }
+    }
+    true
+}
+
+/// Any edge of `a` crosses any edge of `b`.
+pub fn polygons_intersect(a: &[Point], b: &[Point]) -> bool {
+    (0..a.len()).any(|i| (0..b.len()).any(|j|
+        segments_intersect(&a[i], &a[(i+1) % a.len()], &b[j], &b[(j+1) % b.len()])
+    ))
+}
+
+/// Index of polygon's vertex closest to point.
+pub fn closest_index(point: &Point, polygon: &[Point]) -> usize {
+    let mut index: (usize, f32) = (0, f32::MAX);
+
+    polygon.iter().enumerate().for_each(|(i, p)|{
+        let dst = distance(point, p);
+        if dst < index.1 {index.0 = i; index.1 = dst;}
+    });
+
+    index.0
+}
+
+/// Closest point to `p` on segment `a`-`b`, and how far along the segment it is, 0..=1.
+pub fn project_on_segment(p: &Point, a: &Point, b: &Point) -> (Point, f32) {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0.0 { 0.0 } else { (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0) };
+    (Point::new(a.x + dx * t, a.y + dy * t), t)
+}
+
+/// Angle of line from `p1` to `p2`, measured from +x.
+pub fn get_angle(p1: &Point, p2: &Point) -> f32 {
+    (p2.y - p1.y).atan2(p2.x - p1.x)
+}
+
+/// Moves `p` by `distance`, perpendicular to line `p1`-`p2`.
+pub fn move_perp(p1: &Point, p: &Point, p2: &Point, distance: f32) -> Point {
+    let angle = get_angle(p1, p2);
+    Point::new(p.x+distance*angle.sin(), p.y-distance*angle.cos())
+}
+
+/// Splitmix64, used to derive independent seeds from master seed.
+/// Top bit is cleared, so derived seeds fit in TOML integers.
+pub fn mix_seed(seed: u64, salt: u64) -> u64 {
+    let mut z = seed ^ salt.wrapping_mul(0x9E3779B97F4A7C15);
+    z = z.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (z ^ (z >> 31)) >> 1
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(points: &[(f32, f32)]) -> Vec<Point> {
+        points.iter().map(|(x, y)| Point::new(*x, *y) ).collect()
+    }
+
+    #[test]
+    fn segments_cross_in_the_middle() {
+        let x = segment_intersection(&Point::new(0., 0.), &Point::new(2., 2.), &Point::new(0., 2.), &Point::new(2., 0.)).unwrap();
+        assert!(distance(&x, &Point::new(1., 1.)) < 1e-5);
+    }
+
+    #[test]
+    fn touching_and_parallel_segments_dont_cross() {
+        // shared end, T junction and parallel
+        assert!(segment_intersection(&Point::new(0., 0.), &Point::new(1., 0.), &Point::new(1., 0.), &Point::new(1., 1.)).is_none());
+        assert!(segment_intersection(&Point::new(0., 0.), &Point::new(2., 0.), &Point::new(1., 0.), &Point::new(1., 1.)).is_none());
+        assert!(segment_intersection(&Point::new(0., 0.), &Point::new(1., 0.), &Point::new(0., 1.), &Point::new(1., 1.)).is_none());
+    }
+
+    #[test]
+    fn square_is_simple() {
+        assert!(is_simple(&poly(&[(0., 0.), (1., 0.), (1., 1.), (0., 1.)])));
+    }
+
+    #[test]
+    fn figure_eight_is_not_simple() {
+        assert!(!is_simple(&poly(&[(0., 0.), (1., 1.), (1., 0.), (0., 1.)])));
+    }
+
+    #[test]
+    fn small_loop_is_cut_off() {
+        // square with a kink at the bottom edge, going back over itself
+        let p = poly(&[(0., 0.), (6., 0.), (4., -1.), (4., 1.), (10., 0.), (10., 10.), (0., 10.)]);
+        assert!(!is_simple(&p));
+        let r = remove_loops(&p, 3);
+        assert!(is_simple(&r));
+        // two vertices of the loop are replaced by the crossing
+        assert_eq!(r.len(), p.len() - 1);
+    }
+
+    #[test]
+    fn simple_polygon_keeps_its_loops() {
+        let p = poly(&[(0., 0.), (1., 0.), (1., 1.), (0., 1.)]);
+        assert_eq!(remove_loops(&p, 3).len(), 4);
+    }
+}
@@ -0,0 +1,77 @@
+//! Best genomes across generations.
+
+use std::sync::Arc;
+
+use rusty_neat::NN;
+use serde::{Serialize, Deserialize};
+
+use crate::config::Config;
+use crate::fitness::FitnessFunction;
+use crate::genome::Genome;
+use crate::simulation::Simulation;
+use crate::track::Track;
+
+
+/// Genome in the hall of fame, with how it did on every track it drove.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HallEntry {
+    /// Network, with fitness and track of its latest evaluation.
+    pub genome: Genome,
+    /// Mean fitness over every track it drove, so one lucky track doesn't keep it on top.
+    pub mean_fitness: f32,
+    /// Tracks `mean_fitness` is averaged over.
+    pub evaluations: usize,
+}
+
+/// Top `size` genomes seen so far, ranked by mean fitness.
+pub struct HallOfFame {
+    /// Best first.
+    pub entries: Vec<HallEntry>,
+    /// Most entries kept.
+    pub size: usize,
+}
+
+impl HallOfFame {
+    /// Empty hall keeping `size` genomes.
+    pub fn new(size: usize) -> Self {
+        Self { entries: vec![], size }
+    }
+
+    /// Adds candidates, keeping only `size` best. Copies of genomes already inside are skipped.
+    pub fn consider(&mut self, candidates: Vec<Genome>) {
+        for g in candidates {
+            if self.entries.iter().any(|e| same_network(&e.genome.nn, &g.nn) ) { continue; }
+            self.entries.push(HallEntry { mean_fitness: g.fitness, evaluations: 1, genome: g });
+        }
+        self.entries.sort_by(|a, b| b.mean_fitness.total_cmp(&a.mean_fitness) );
+        self.entries.truncate(self.size);
+    }
+
+    /// Drives every entry on given track and folds the result into its mean fitness.
+    /// Time limit is the one of `generation`, so results compare with that generation's cars.
+    /// `fitness` has to be the one entries were first scored with.
+    pub fn reevaluate(&mut self, config: &Config, fitness: Arc<dyn FitnessFunction>, track: Track, generation: usize) {
+        if self.entries.is_empty() { return; }
+        let agents = self.entries.iter().map(|e| e.genome.nn.clone() ).collect();
+        let mut sim = Simulation::with_agents(config.clone(), agents, track);
+        sim.set_fitness(fitness);
+        sim.generation = generation;
+        sim.run_episode(config.population.dt);
+
+        self.entries.iter_mut().zip(sim.cars.iter()).for_each(|(e, c)| {
+            e.mean_fitness = (e.mean_fitness * e.evaluations as f32 + sim.fitness_of(c)) / (e.evaluations + 1) as f32;
+            e.evaluations += 1;
+        });
+        self.entries.sort_by(|a, b| b.mean_fitness.total_cmp(&a.mean_fitness) );
+    }
+
+    /// Highest mean fitness.
+    pub fn best(&self) -> Option<&HallEntry> {
+        self.entries.first()
+    }
+}
+
+// reinjected copies come back as candidates, node values and fitness don't matter here
+fn same_network(a: &NN, b: &NN) -> bool {
+    a.connections == b.connections && a.nodes.len() == b.nodes.len() && a.nodes.keys().all(|k| b.nodes.contains_key(k) )
+}
@@ -0,0 +1,46 @@
+//! Cars learning to drive with [rusty_neat](https://github.com/HVisMyLife/rusty_neat).
+//!
+//! - [`geometry`] points and polygon helpers
+//! - [`track`] `Track` and random track generation
+//! - [`centreline`] arc-length position along the track
+//! - [`car`] vehicle physics
+//! - [`sensor`] distance rays cars see the track with
+//! - [`simulation`] training loop, independent from rendering
+//! - [`fitness`] what cars are rewarded for
+//! - [`config`] and [`checkpoint`] run settings and saved training state
+//! - [`genome`] single network files
+//! - [`hall_of_fame`] best genomes across generations
+//! - [`validation`] held-out tracks, to tell driving from memorising
+//! - [`stats`] per-generation statistics
+//!
+//! ```no_run
+//! use neat_race::{Config, Simulation};
+//!
+//! let config = Config::default();
+//! let mut sim = Simulation::new(config, 42).unwrap();
+//! loop {
+//!     let stats = sim.run_generation(sim.config.population.dt);
+//!     println!("{}: {}", stats.generation, stats.best_fitness);
+//! }
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod geometry;
+pub mod track;
+pub mod centreline;
+pub mod car;
+pub mod sensor;
+pub mod config;
+pub mod checkpoint;
+pub mod simulation;
+pub mod fitness;
+pub mod genome;
+pub mod hall_of_fame;
+pub mod validation;
+pub mod stats;
+
+pub use config::Config;
+pub use checkpoint::Checkpoint;
+pub use simulation::Simulation;
+pub use rusty_neat;
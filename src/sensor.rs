@@ -0,0 +1,69 @@
+//! Distance sensors.
+
+use std::f32::consts::PI;
+
+use crate::geometry::Point;
+use crate::track::Track;
+
+
+/// Closest intersection of a ray with polygon's edges.
+pub fn raycast(point: &Point, angle: f32, polygon: &[Point]) -> Option<Point> {
+    let mut min_t = f32::MAX;
+    let mut intersection: Option<Point> = None;
+
+    let x = point.x;
+    let y = point.y;
+    let dx = angle.cos();
+    let dy = angle.sin();
+
+    for i in 0..polygon.len() {
+        let j = (i + 1) % polygon.len();
+        let ax = polygon[i].x;
+        let ay = polygon[i].y;
+        let bx = polygon[j].x;
+        let by = polygon[j].y;
+
+        let t = ((ay - y) * (bx - ax) - (ax - x) * (by - ay)) / (dy * (bx - ax) - dx * (by - ay));
+
+        if t >= 0.0 && (dx * t + x) >= ax.min(bx) && (dx * t + x) <= ax.max(bx) && (dy * t + y) >= ay.min(by) && (dy * t + y) <= ay.max(by) && t < min_t {
+            min_t = t;
+            intersection = Some(Point::new(dx * t + x, dy * t + y));
+        }
+    }
+
+    intersection
+}
+
+/// Casts `amount` rays evenly around the car, starting from its front.
+/// Returns distance to the closest wall for every ray, and the hit points.
+/// Rays that hit nothing give `f32::MAX`.
+pub fn raywrap(point: &Point, angle: f32, amount: usize, track: &Track) -> (Vec<f32>, Vec<Point>) {
+    let mut dsts: Vec<f32> = vec![];
+    let mut pp: Vec<Point> = vec![];
+
+    for i in 0..amount {
+        
+        let ray1 = raycast(point, (2.0*PI/amount as f32)*i as f32+PI/2.0+angle, &track.inner);
+        let ray2 = raycast(point, (2.0*PI/amount as f32)*i as f32+PI/2.0+angle, &track.outer);
+
+        if let (Some(ray1), Some(ray2)) = (ray1.clone(), ray2.clone()) {
+            let d1 = ((ray1.x - point.x).powi(2)+(ray1.y - point.y).powi(2)).sqrt();
+            let d2 = ((ray2.x - point.x).powi(2)+(ray2.y - point.y).powi(2)).sqrt();
+            if d1 < d2 {dsts.push(d1);pp.push(ray1);}
+            else {dsts.push(d2);pp.push(ray2);}
+        }
+        else if let Some(ray1) = ray1 {
+            let d = ((ray1.x - point.x).powi(2)+(ray1.y - point.y).powi(2)).sqrt();
+            dsts.push(d);
+            pp.push(ray1);
+        }
+        else if let Some(ray2) = ray2 {
+            let d = ((ray2.x - point.x).powi(2)+(ray2.y - point.y).powi(2)).sqrt();
+            dsts.push(d);
+            pp.push(ray2);
+        }
+        else {dsts.push(f32::MAX);pp.push(Point::new(-100.0, -100.0));}
+
+    }
+    (dsts, pp)
+}
@@ -0,0 +1,483 @@
+//! Training loop.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use rayon::prelude::*;
+use rusty_neat::{NeatIntermittent, NN};
+use rusty_neat::rand::{rngs::Xoshiro256PlusPlus, SeedableRng as _};
+
+use crate::geometry::{point_in_polygon, mix_seed};
+use crate::track::Track;
+use crate::car::{Car, Termination};
+use crate::sensor::raywrap;
+use crate::checkpoint::Checkpoint;
+use crate::config::{Config, PopulationConfig, WrongWay};
+use crate::stats::{GenerationStats, mean_median, CRASH_SECTIONS};
+use crate::genome::Genome;
+use crate::hall_of_fame::HallOfFame;
+use crate::fitness::{FitnessContext, FitnessFunction};
+
+
+// salt of simulation rng stream, tracks use generation number as salt
+const RNG_SALT: u64 = u64::MAX;
+// progress in pixels cars get for free before min_speed applies
+const STALL_SLACK: f32 = 150.0;
+
+/// Whole training state, independent from any rendering.
+/// Window app and headless mode are just different ways of driving it.
+///
+/// Everything random (tracks, race directions, mutation, crossover, reinjection) is derived from `seed`,
+/// so the same seed always gives the same run.
+pub struct Simulation {
+    /// Settings of the run, don't change them while it's running.
+    pub config: Config,
+    /// Current track, its seed is in `track.info`.
+    pub track: Track,
+    /// Population, agent `i` drives car `i`.
+    pub neat: NeatIntermittent,
+    /// Cars of current episode.
+    pub cars: Vec<Car>,
+    /// Number of current generation, from 0.
+    pub generation: usize,
+    /// Cars still driving.
+    pub alive_sum: usize,
+    /// Steps done in current generation.
+    pub tick: usize,
+    /// Master seed, every track and the simulation rng are derived from it.
+    pub seed: u64,
+    /// Index of track current generation drives, out of `population.tracks`.
+    pub episode: usize,
+    /// Best genomes of the run so far.
+    pub hall_of_fame: HallOfFame,
+    /// Simulated seconds since current generation started.
+    time: f64,
+    /// Only for stats, timing never depends on it.
+    wall_clock: Instant,
+    rng: ChaCha8Rng,
+    /// Fitness of every agent on already finished tracks of current generation.
+    scores: Vec<Vec<f32>>,
+    fitness: Arc<dyn FitnessFunction>,
+    /// Loaded from `track.file`, replaces every generated track.
+    fixed_track: Option<Track>,
+}
+
+impl Simulation {
+    /// Fails only when `track.file` can't be loaded.
+    pub fn new(config: Config, seed: u64) -> io::Result<Self> {
+        let fixed_track = load_fixed(&config)?;
+        let track = fixed_track.clone().unwrap_or_else(|| Track::from_seed(&config, mix_seed(seed, 0)) );
+        let n = &config.network;
+        let mut rng = ChaCha8Rng::seed_from_u64(mix_seed(seed, RNG_SALT));
+        let mut neat = NeatIntermittent::new_with(
+            &NN::new(n.rays + 2, 2, None, n.recurrence, n.recurrence_weight,
+                n.activation_io.clone(), &n.activations),
+            config.population.entities, config.population.species, &mut neat_rng(&mut rng) );
+        neat.speciate();
+
+        Ok(Self::build(config, neat, track, fixed_track, 0, seed, rng))
+    }
+
+    /// Restores training state, current generation starts from the beginning.
+    /// Run continues with config it was saved with, so its `track.file` has to still be there.
+    pub fn from_checkpoint(c: Checkpoint) -> io::Result<Self> {
+        let mut neat = NeatIntermittent {
+            agents: c.agents,
+            size: c.size,
+            innov_id: c.innov_id,
+            innov_table: c.innov_table.into_iter().collect(),
+            species_threshold: c.species_threshold,
+            species_amount: c.species_amount,
+            species_table: HashMap::new(),
+        };
+        neat.speciate();
+
+        let fixed_track = load_fixed(&c.config)?;
+        let track = fixed_track.clone().unwrap_or_else(|| Track::new(&c.config, (c.inner, c.outer), c.track_seed, Some(c.config.track.generator)) );
+        let mut s = Self::build(c.config, neat, track, fixed_track, c.generation, c.seed, c.rng);
+        s.hall_of_fame.entries = c.hall_of_fame;
+        Ok(s)
+    }
+
+    /// Fixed, already trained agents on given track, for evaluation and replay.
+    /// Nothing evolves as long as `next_gen` isn't called, and there is only that one track.
+    pub fn with_agents(mut config: Config, agents: Vec<NN>, track: Track) -> Self {
+        let mut neat = NeatIntermittent {
+            size: agents.len(),
+            agents,
+            innov_id: 0,
+            innov_table: HashMap::new(),
+            species_threshold: 3.,
+            species_amount: config.population.species,
+            species_table: HashMap::new(),
+        };
+        neat.speciate();
+        config.population.tracks = 1;
+
+        let seed = track.info.seed;
+        Self::build(config, neat, track, None, 0, seed, ChaCha8Rng::seed_from_u64(mix_seed(seed, RNG_SALT)))
+    }
+
+    fn build(config: Config, neat: NeatIntermittent, track: Track, fixed_track: Option<Track>,
+        generation: usize, seed: u64, rng: ChaCha8Rng) -> Self {
+        let mut s = Self {
+            fitness: config.fitness.function.build(&config.fitness.weights).into(),
+            hall_of_fame: HallOfFame::new(config.hall_of_fame.size),
+            config,
+            track, neat,
+            cars: vec![],
+            generation,
+            alive_sum: 0,
+            seed,
+            time: 0.0,
+            tick: 0,
+            wall_clock: Instant::now(),
+            rng,
+            episode: 0,
+            scores: vec![],
+            fixed_track,
+        };
+        s.restart();
+        s
+    }
+
+    /// Saveable state, resuming from it restarts current generation.
+    pub fn checkpoint(&self) -> Checkpoint {
+        // loaded generation starts over from its first track
+        let track = match self.episode {
+            0 => self.track.clone(),
+            _ => self.track_of(self.generation, 0),
+        };
+        let (track_seed, (inner, outer)) = (track.info.seed, track.boundaries());
+        Checkpoint {
+            config: self.config.clone(),
+            generation: self.generation,
+            seed: self.seed,
+            track_seed, inner, outer,
+            rng: self.rng.clone(),
+            agents: self.neat.agents.clone(),
+            size: self.neat.size,
+            innov_id: self.neat.innov_id,
+            innov_table: self.neat.innov_table.iter().map(|(k, v)| (k.clone(), *v) ).collect(),
+            species_threshold: self.neat.species_threshold,
+            species_amount: self.neat.species_amount,
+            hall_of_fame: self.hall_of_fame.entries.clone(),
+        }
+    }
+
+    /// Replaces fitness function chosen in config, hall of fame is re-evaluated with it too.
+    pub fn set_fitness(&mut self, fitness: Arc<dyn FitnessFunction>) {
+        self.fitness = fitness;
+    }
+
+    /// Fitness function in use, to score other simulations the same way.
+    pub fn fitness(&self) -> Arc<dyn FitnessFunction> {
+        self.fitness.clone()
+    }
+
+    /// Raw fitness of car on current track.
+    pub fn fitness_of(&self, car: &Car) -> f32 {
+        score(self.fitness.as_ref(), car, &FitnessContext { track: &self.track }, &self.config.population)
+    }
+
+    /// Simulated time since current generation started, sum of all step's dt.
+    /// Episode timing depends only on it, never on wall clock.
+    pub fn elapsed(&self) -> f32 {
+        self.time as f32
+    }
+
+    /// Current track is done after timeout or when every car is dead.
+    pub fn episode_over(&self) -> bool {
+        let p = &self.config.population;
+        self.elapsed() > p.gen_len + self.generation as f32 * p.gen_len_growth || self.alive_sum < 1
+    }
+
+    /// Generation ends when the last of its tracks is done.
+    pub fn generation_over(&self) -> bool {
+        self.episode_over() && self.episode + 1 >= self.config.population.tracks
+    }
+
+    /// Single sensor + network + physics step for every living car.
+    /// Moves cars to the next track of generation first, if current one is done.
+    pub fn step(&mut self, dt: f32) {
+        if self.episode_over() && !self.generation_over() { self.next_track(); }
+        self.time += dt as f64;
+        self.tick += 1;
+        let track = &self.track;
+        let centreline = &track.centreline;
+        let sign = track.direction.sign();
+        let race_laps = self.config.population.race_laps;
+        let g = &self.config.gates;
+        let gate_spacing = centreline.length / g.count.max(1) as f32;
+        let rays = self.config.network.rays;
+        let time = self.elapsed();
+
+        let mut ins: Vec<Vec<f32>> = vec![vec![]; self.cars.len()];
+        self.cars.par_iter_mut().zip_eq(ins.par_iter_mut()).for_each(|(c, i)| {
+            if c.alive {
+            let (mut rv, _rp) = raywrap(c.get_position(), *c.get_angle(), rays, track);
+
+            // progress along centreline, in race direction
+            let arc = centreline.project(c.get_position());
+            c.progress += sign * centreline.delta(c.arc, arc);
+            c.arc = arc;
+            c.furthest = c.furthest.max(c.progress);
+            // cars start on the gate, so every lap of progress is a crossing of it
+            if c.laps(centreline.length) > c.lap_times.len() {
+                c.lap_times.push(time - c.lap_start);
+                c.lap_start = time;
+                c.finished = race_laps > 0 && c.lap_times.len() >= race_laps;
+            }
+            // progress only counts once, so gates are passed in order
+            if g.count > 0 && c.progress >= (c.gates + 1) as f32 * gate_spacing {
+                c.gates += 1;
+                c.deadline += g.extension;
+            }
+            // speed in forward direction
+            c.agility += c.get_velocity().length_project(c.get_angle());
+
+            i.push(c.get_velocity().length());
+            i.push(*c.get_velocity_ang());
+            i.append(&mut rv); }
+        } );
+        self.neat.forward(&ins);
+
+        // some slack, so cars can get going first
+        let min_progress = self.elapsed() * self.config.population.min_speed - STALL_SLACK;
+        let gates_on = self.config.gates.count > 0;
+        let time = self.elapsed();
+        let (fitness, ctx) = (self.fitness.as_ref(), FitnessContext { track });
+        let p = &self.config.population;
+        self.cars.par_iter_mut().zip_eq(self.neat.agents.par_iter_mut()).for_each(|(c,a)|{
+            if c.alive {
+            let o = a.get_outputs();
+            c.acc_forward(o[0]*100., dt);
+            c.acc_ang(o[1]*4., dt);
+            c.update(dt);
+            // death check, first reason wins
+            c.wrong_way |= c.furthest - c.progress > p.wrong_way_limit;
+            c.spin = if c.get_velocity_ang().abs() > p.spin_limit { c.spin + dt } else { 0.0 };
+            let stalled = if gates_on { time > c.deadline } else { c.progress < min_progress };
+            c.termination =
+                if c.points.iter().any(|p| point_in_polygon(p, &track.inner)) { Some(Termination::InnerWall) }
+                else if c.points.iter().any(|p| !point_in_polygon(p, &track.outer)) { Some(Termination::OuterWall) }
+                else if c.finished { Some(Termination::Finished) }
+                else if c.wrong_way && p.wrong_way == WrongWay::Kill { Some(Termination::WrongWay) }
+                else if p.spin_limit > 0.0 && c.spin > p.spin_time { Some(Termination::SpinOut) }
+                else if stalled { Some(Termination::Stall) }
+                else { None };
+            c.alive = c.termination.is_none();
+            a.active = c.alive;
+            a.fitness = score(fitness, c, &ctx, p);}
+        });
+
+        self.alive_sum = self.cars.iter().filter(|c| c.alive ).count();
+        // survivors keep driving if episode is continued, so they stay alive
+        if self.episode_over() {
+            self.cars.iter_mut().filter(|c| c.alive ).for_each(|c| c.termination = Some(Termination::Timeout) );
+        }
+        if self.generation_over() && self.config.population.tracks > 1 { self.aggregate_fitness(); }
+    }
+
+    // fitness over all tracks of generation, current one is read from cars so it can be repeated
+    fn aggregate_fitness(&mut self) {
+        let p = &self.config.population;
+        let fitness: Vec<f32> = self.cars.iter().map(|c| self.fitness_of(c) ).collect();
+        self.neat.agents.iter_mut().zip(fitness).enumerate().for_each(|(i, (a, f))| {
+            let mut all = self.scores.get(i).cloned().unwrap_or_default();
+            all.push(f);
+            a.fitness = p.aggregate.apply(&all, p.trim);
+        });
+    }
+
+    fn next_track(&mut self) {
+        let fitness: Vec<f32> = self.cars.iter().map(|c| self.fitness_of(c) ).collect();
+        self.scores.resize(self.cars.len(), vec![]);
+        self.scores.iter_mut().zip(fitness).for_each(|(s, f)| s.push(f) );
+        self.episode += 1;
+        self.set_track(self.track_of(self.generation, self.episode));
+    }
+
+    fn track_of(&self, generation: usize, episode: usize) -> Track {
+        match &self.fixed_track {
+            Some(t) => t.clone(),
+            None => Track::from_seed(&self.config, self.track_seed_of(generation, episode)),
+        }
+    }
+
+    // first track of generation keeps the seed it had before there were more tracks
+    fn track_seed_of(&self, generation: usize, episode: usize) -> u64 {
+        let s = mix_seed(self.seed, generation as u64);
+        if episode == 0 { s } else { mix_seed(s, episode as u64) }
+    }
+
+    /// Breeds next generation and places it on a fresh track.
+    /// Hall of fame takes best of finished generation, then drives the new track too.
+    pub fn next_gen(&mut self) {
+        let mut best: Vec<&NN> = self.neat.agents.iter().collect();
+        best.sort_by(|a, b| b.fitness.total_cmp(&a.fitness) );
+        let candidates = best.into_iter().take(self.hall_of_fame.size)
+            .map(|nn| Genome { fitness: nn.fitness, generation: self.generation, track_seed: self.track.info.seed, nn: nn.clone() } )
+            .collect();
+        self.hall_of_fame.consider(candidates);
+
+        self.time = 0.0;
+        self.tick = 0;
+        self.wall_clock = Instant::now();
+        self.generation += 1;
+        self.episode = 0;
+        self.scores.clear();
+
+        self.track = self.track_of(self.generation, 0);
+
+        let shaping = self.config.fitness.shaping;
+        self.neat.agents.iter_mut().for_each(|a| a.fitness = shaping.apply(a.fitness) );
+        let mut rng = neat_rng(&mut self.rng);
+        self.neat.next_gen_with(&mut rng);
+        self.neat.mutate_with(None, &mut rng);
+        self.reinject();
+        self.neat.speciate();
+
+        self.hall_of_fame.reevaluate(&self.config, self.fitness.clone(), self.track.clone(), self.generation);
+        self.spawn_cars();
+    }
+
+    // best hall of fame genomes replace random offspring, unmutated
+    fn reinject(&mut self) {
+        let len = self.neat.agents.len();
+        let amount = self.config.hall_of_fame.reinject.min(self.hall_of_fame.entries.len()).min(len);
+        let idxs = rand::seq::index::sample(&mut self.rng, len, amount);
+        idxs.iter().zip(self.hall_of_fame.entries.iter()).for_each(|(i, e)| {
+            self.neat.agents[i] = e.genome.nn.clone();
+            self.neat.agents[i].active = true;
+        });
+    }
+
+    /// Starts current track over.
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.tick = 0;
+        self.neat.agents.iter_mut().for_each(|a| a.active = true );
+        self.spawn_cars();
+    }
+
+    /// Replaces track and starts it over.
+    pub fn set_track(&mut self, track: Track) {
+        self.track = track;
+        self.restart();
+    }
+
+    /// Steps until generation is over, without breeding.
+    pub fn run_episode(&mut self, dt: f32) {
+        while !self.generation_over() { self.step(dt); }
+    }
+
+    /// Steps until generation is over, then moves to the next one.
+    /// Returns stats of finished generation.
+    pub fn run_generation(&mut self, dt: f32) -> GenerationStats {
+        self.run_episode(dt);
+        let stats = self.stats();
+        self.next_gen();
+        stats
+    }
+
+    /// Living car furthest along the track.
+    pub fn leader(&self) -> Option<&Car> {
+        self.cars.iter().filter(|c| c.alive ).max_by(|a, b| a.progress.total_cmp(&b.progress) )
+    }
+
+    /// Fittest agent of current generation, meant to be taken right before `next_gen`.
+    pub fn champion(&self) -> Genome {
+        let nn = self.neat.agents.iter().max_by(|a, b| a.fitness.total_cmp(&b.fitness) ).unwrap();
+        Genome { fitness: nn.fitness, generation: self.generation, track_seed: self.track.info.seed, nn: nn.clone() }
+    }
+
+    /// Stats of current generation, meant to be taken right before `next_gen`.
+    pub fn stats(&self) -> GenerationStats {
+        let fitness: Vec<f32> = self.neat.agents.iter().map(|a| a.fitness ).collect();
+        let (mean_fitness, median_fitness) = mean_median(&fitness);
+        let champion = self.neat.agents.iter().max_by(|a, b| a.fitness.total_cmp(&b.fitness) );
+        let mut species_sizes: Vec<(usize, usize)> = self.neat.species_table.iter().map(|(k, s)| (*k, s.size) ).collect();
+        species_sizes.sort();
+        let mut terminations = BTreeMap::new();
+        self.cars.iter().filter_map(|c| c.termination ).for_each(|t| *terminations.entry(t).or_insert(0) += 1 );
+        // sections go by where on the lap cars crashed, measured from the start line in driving direction
+        let mut crash_sections = vec![0; CRASH_SECTIONS];
+        let (length, sign) = (self.track.centreline.length, self.track.direction.sign());
+        let start_arc = self.track.centreline.project(&self.track.start.0);
+        self.cars.iter().filter(|c| c.crashed() ).for_each(|c| {
+            let along = (sign * (c.arc - start_arc)).rem_euclid(length);
+            crash_sections[((along / length * CRASH_SECTIONS as f32) as usize).min(CRASH_SECTIONS - 1)] += 1;
+        });
+
+        GenerationStats {
+            generation: self.generation,
+            best_fitness: fitness.iter().cloned().fold(0.0, f32::max),
+            mean_fitness, median_fitness,
+            best_progress: self.cars.iter().map(|c| c.progress / self.track.centreline.length ).fold(0.0, f32::max),
+            best_agility: self.cars.iter().map(|c| c.agility ).fold(f32::MIN, f32::max),
+            alive: self.alive_sum,
+            finished: self.cars.iter().filter(|c| c.finished ).count(),
+            best_lap_time: self.cars.iter().filter_map(|c| c.best_lap() ).reduce(f32::min),
+            best_gates: self.cars.iter().map(|c| c.gates ).max().unwrap_or(0),
+            terminations, crash_sections,
+            species_count: species_sizes.len(),
+            species_sizes,
+            champion_nodes: champion.map_or(0, |a| a.nodes.len() ),
+            champion_connections: champion.map_or(0, |a| a.connections.values().filter(|c| c.active ).count() ),
+            track_seed: self.track.info.seed,
+            hall_of_fame_best: self.hall_of_fame.best().map_or(0.0, |e| e.mean_fitness ),
+            sim_time: self.elapsed(),
+            wall_time: self.wall_clock.elapsed().as_secs_f32(),
+            validation: None,
+        }
+    }
+
+    fn spawn_cars(&mut self) {
+        let (car, shape) = (&self.config.car, self.config.car_shape());
+        let (gates_on, initial_time) = (self.config.gates.count > 0, self.config.gates.initial_time);
+        let (position, angle) = self.track.start.clone();
+        let arc = self.track.centreline.project(&position);
+
+        // networks start every track with clean memory, like they did on the first one
+        self.neat.agents.iter_mut().flat_map(|a| a.nodes.values_mut() ).for_each(|n| {
+            (n.value, n.value_old, n.value_gate) = (0.0, 0.0, 0.0);
+        });
+
+        self.cars.clear();
+        (0..self.neat.agents.len()).for_each(|_| {
+            let mut c = Car::new(
+                shape.clone(),
+                position.clone(),
+                angle,
+                car.mass,
+                car.friction);
+            c.arc = arc;
+            if gates_on { c.deadline = initial_time; }
+            self.cars.push(c);
+        });
+        self.alive_sum = self.cars.len();
+    }
+}
+
+// error says which file, as it's the one from config and not something caller passed in
+// rusty_neat is on a newer rand than ChaCha8Rng, so it gets its own rng seeded from ours
+fn neat_rng(rng: &mut ChaCha8Rng) -> Xoshiro256PlusPlus {
+    Xoshiro256PlusPlus::seed_from_u64(rng.random())
+}
+
+fn load_fixed(config: &Config) -> io::Result<Option<Track>> {
+    config.track.file.as_ref().map(|f| Track::load(config, Path::new(f))
+        .map_err(|e| io::Error::new(e.kind(), format!("can't load track {}: {}", f, e))) ).transpose()
+}
+
+// raw fitness, with wrong way penalty
+fn score(fitness: &dyn FitnessFunction, car: &Car, ctx: &FitnessContext, p: &PopulationConfig) -> f32 {
+    let f = fitness.fitness(car, ctx).max(0.001);
+    if car.wrong_way && p.wrong_way == WrongWay::Penalty { (f * p.wrong_way_penalty).max(0.001) } else { f }
+}
@@ -0,0 +1,138 @@
+//! Per-generation statistics.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Serialize, Deserialize};
+
+use crate::car::Termination;
+use crate::validation::ValidationResult;
+
+
+/// Lap is split into this many sections for crash histogram.
+pub const CRASH_SECTIONS: usize = 20;
+
+/// Summary of finished generation, one JSON line in run's `stats.jsonl`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GenerationStats {
+    /// Generation these are from.
+    pub generation: usize,
+    /// Fitness after aggregating over tracks, before shaping.
+    pub best_fitness: f32,
+    /// Mean of the same fitness.
+    pub mean_fitness: f32,
+    /// Median of the same fitness.
+    pub median_fitness: f32,
+    /// Best track progress, in laps.
+    pub best_progress: f32,
+    /// Highest accumulated forward speed.
+    pub best_agility: f32,
+    /// Cars still alive when generation ended.
+    pub alive: usize,
+    /// Cars that drove all `race_laps`.
+    pub finished: usize,
+    /// Fastest lap any car drove, simulated seconds.
+    pub best_lap_time: Option<f32>,
+    /// Most checkpoint gates passed.
+    pub best_gates: usize,
+    /// How many cars ended their episode for every reason.
+    pub terminations: BTreeMap<Termination, usize>,
+    /// Wall crashes in every `CRASH_SECTIONS`th of lap, counted from start/finish line.
+    pub crash_sections: Vec<usize>,
+    /// Number of species.
+    pub species_count: usize,
+    /// (species id, size), sorted by id.
+    pub species_sizes: Vec<(usize, usize)>,
+    /// Nodes and active connections of the fittest network.
+    pub champion_nodes: usize,
+    /// See `champion_nodes`.
+    pub champion_connections: usize,
+    /// Track of the last episode.
+    pub track_seed: u64,
+    /// Mean fitness of the best hall of fame genome, 0 if it's empty.
+    pub hall_of_fame_best: f32,
+    /// Simulated length of generation.
+    pub sim_time: f32,
+    /// Real time generation took.
+    pub wall_time: f32,
+    /// Champion's result on validation tracks, only in generations it was validated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ValidationResult>,
+}
+
+impl GenerationStats {
+    /// Appends single JSON line to `path`.
+    pub fn append(&self, path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(self).map_err(io::Error::other)?)
+    }
+}
+
+/// How fitness from multiple tracks is combined into one.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    /// Plain mean of all tracks.
+    Mean,
+    /// Worst track, only genomes that drive everywhere score high.
+    Min,
+    /// Mean without `trim` fraction of best and worst tracks.
+    TrimmedMean,
+}
+
+impl Aggregate {
+    /// Combined fitness, 0 for no tracks.
+    pub fn apply(&self, values: &[f32], trim: f32) -> f32 {
+        if values.is_empty() { return 0.0; }
+        match self {
+            Aggregate::Mean => values.iter().sum::<f32>() / values.len() as f32,
+            Aggregate::Min => values.iter().cloned().fold(f32::MAX, f32::min),
+            Aggregate::TrimmedMean => {
+                let mut sorted = values.to_vec();
+                sorted.sort_by(|a, b| a.total_cmp(b) );
+                let cut = (sorted.len() as f32 * trim) as usize;
+                let kept = &sorted[cut..sorted.len() - cut];
+                kept.iter().sum::<f32>() / kept.len() as f32
+            }
+        }
+    }
+}
+
+/// Mean and median.
+pub fn mean_median(values: &[f32]) -> (f32, f32) {
+    if values.is_empty() { return (0.0, 0.0); }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b) );
+    let mid = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) { (sorted[mid-1] + sorted[mid]) / 2.0 } else { sorted[mid] };
+    (sorted.iter().sum::<f32>() / sorted.len() as f32, median)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trimmed_mean_keeps_everything_when_too_few_tracks_to_cut() {
+        assert_eq!(Aggregate::TrimmedMean.apply(&[3.0], 0.25), 3.0);
+        assert_eq!(Aggregate::TrimmedMean.apply(&[1.0, 3.0], 0.4), 2.0);
+        assert_eq!(Aggregate::TrimmedMean.apply(&[1.0, 2.0, 6.0], 0.3), 3.0);
+    }
+
+    #[test]
+    fn trimmed_mean_cuts_both_ends() {
+        assert_eq!(Aggregate::TrimmedMean.apply(&[10.0, 1.0, 2.0, 4.0], 0.25), 3.0);
+        // highest trim config allows leaves the median
+        assert_eq!(Aggregate::TrimmedMean.apply(&[9.0, 0.0, 5.0], 0.49), 5.0);
+    }
+
+    #[test]
+    fn no_tracks_aggregate_to_zero() {
+        for a in [Aggregate::Mean, Aggregate::Min, Aggregate::TrimmedMean] {
+            assert_eq!(a.apply(&[], 0.2), 0.0);
+        }
+    }
+}
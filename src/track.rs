@@ -1,27 +1,339 @@
-use crate::global::Point;
+//! Tracks and their random generation.
+
+use std::{fmt, fs, io};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::f32::consts::PI;
+use std::path::Path;
+
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::centreline::{Centreline, Direction};
+use crate::geometry::{distance, distance_to_polygon, get_angle, is_simple, move_perp, point_in_polygon, polygons_intersect, remove_loops, signed_area, Point};
+use crate::config::{Config, TrackConfig};
+
+
+/// Bumped every time layout of `Track` changes, written at the start of bincode files and as `version` in JSON ones.
+pub const TRACK_VERSION: u32 = 1;
+
+/// Driveable track: both boundaries and everything derived from them,
+/// shared by sensors, physics, fitness and drawing.
+/// Saved whole, so files are readable on their own, but only boundaries and `info` are read back.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Track {
+    /// Inner boundary, closed polygon.
+    pub inner: Vec<Point>,
+    /// Outer boundary, around `inner`.
+    pub outer: Vec<Point>,
+    /// Middle line between boundaries, progress is measured along it.
+    pub centreline: Centreline,
+    /// Distance between boundaries at every centreline point.
+    pub widths: Vec<f32>,
+    /// Way cars race around it.
+    pub direction: Direction,
+    /// Start/finish line, laps are counted when crossing it in race direction.
+    pub start_gate: (Point, Point),
+    /// Position and angle cars spawn with, in the middle of start gate facing race direction.
+    pub start: (Point, f32),
+    /// Checkpoint gates in race order, the last one lies on start/finish line.
+    pub gates: Vec<(Point, Point)>,
+    /// Seed, generator and shape measures.
+    pub info: TrackInfo,
+}
+
+/// Facts about a track, for stats and telling tracks apart.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrackInfo {
+    /// Seed it was generated from, race direction is picked from it too.
+    pub seed: u64,
+    /// `None` for tracks that weren't generated.
+    pub generator: Option<Generator>,
+    /// Centreline length, one lap.
+    pub length: f32,
+    /// Narrowest spot of `Track::widths`.
+    pub min_width: f32,
+    /// Mean of `Track::widths`.
+    pub mean_width: f32,
+    /// Mean absolute turning of centreline, radians per pixel.
+    pub mean_curvature: f32,
+    /// Sharpest turn, radians per pixel.
+    pub max_curvature: f32,
+}
+
+// JSON file layout, version next to the track's own fields
+#[derive(Serialize)]
+struct JsonOut<'a> {
+    version: u32,
+    #[serde(flatten)]
+    track: &'a Track,
+}
+
+// the part of JSON file that is read back, the rest is derived again
+#[derive(Deserialize)]
+struct JsonIn {
+    version: u32,
+    inner: Vec<Point>,
+    outer: Vec<Point>,
+    info: TrackInfo,
+}
+
+impl Track {
+    /// Track generated from `seed` with `config.track`, same one `track_from_seed` gives.
+    pub fn from_seed(config: &Config, seed: u64) -> Self {
+        Self::new(config, track_from_seed(&config.track, seed), seed, Some(config.track.generator))
+    }
+
+    /// Derives the rest from inner and outer boundary, direction comes from `track.direction` and `seed`.
+    /// Boundaries can go either way around, clockwise ones are reversed.
+    pub fn new(config: &Config, (inner, outer): (Vec<Point>, Vec<Point>), seed: u64, generator: Option<Generator>) -> Self {
+        // start gate goes from inner boundary to the left of it, that's only across the track counter-clockwise
+        let (inner, outer) = (counter_clockwise(inner), counter_clockwise(outer));
+        let centreline = Centreline::new(&inner, &outer);
+        // centreline points are midpoints of inner vertices, so they are half of the width away from them
+        let widths: Vec<f32> = inner.iter().zip(&centreline.points).map(|(p, c)| 2.0 * distance(p, c) ).collect();
+        let direction = config.track.direction.pick(seed);
+
+        let m = inner.len() / 2;
+        let start_gate = (inner[m].clone(), move_perp(&inner[m-1], &inner[m], &inner[m+1], widths[m]));
+        // car faces +y at angle 0, so PI/2 off the way it should go
+        let start = (start_gate.0.mid(&start_gate.1), get_angle(&inner[m-1], &inner[m+1]) - direction.sign() * PI/2.0);
+
+        // spread evenly from start/finish line, in race direction
+        let count = config.gates.count;
+        let first = centreline.project(&start.0);
+        let spacing = direction.sign() * centreline.length / count.max(1) as f32;
+        let gates = (1..=count).map(|k| {
+            let arc = first + k as f32 * spacing;
+            let w = 2.0 * distance_to_polygon(&centreline.point_at(arc).0, &inner);
+            centreline.gate_at(arc, w)
+        }).collect();
+
+        let (mean_curvature, max_curvature) = curvature(&centreline);
+        let info = TrackInfo {
+            seed, generator,
+            length: centreline.length,
+            min_width: widths.iter().cloned().fold(f32::MAX, f32::min),
+            mean_width: widths.iter().sum::<f32>() / widths.len().max(1) as f32,
+            mean_curvature, max_curvature,
+        };
+        Self { inner, outer, centreline, widths, direction, start_gate, start, gates, info }
+    }
+
+    /// JSON for `.json` files, bincode with version in front of it otherwise.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if is_json(path) {
+            let out = JsonOut { version: TRACK_VERSION, track: self };
+            return fs::write(path, serde_json::to_string_pretty(&out).map_err(io::Error::other)?);
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut file, &TRACK_VERSION).map_err(io::Error::other)?;
+        bincode::serialize_into(&mut file, self).map_err(io::Error::other)
+    }
+
+    /// Reads track saved by `save` and checks it against `track.min_width`.
+    /// Everything but boundaries and `info` is derived again, so gates and direction follow `config`.
+    pub fn load(config: &Config, path: &Path) -> io::Result<Self> {
+        let (inner, outer, info) = if is_json(path) {
+            let t: JsonIn = serde_json::from_str(&fs::read_to_string(path)?).map_err(io::Error::other)?;
+            check_version(t.version)?;
+            (t.inner, t.outer, t.info)
+        } else {
+            let mut file = BufReader::new(File::open(path)?);
+            check_version(bincode::deserialize_from(&mut file).map_err(io::Error::other)?)?;
+            // bincode has no field names, so it's read whole
+            let t: Track = bincode::deserialize_from(file).map_err(io::Error::other)?;
+            (t.inner, t.outer, t.info)
+        };
+        check_track(&inner, &outer, config.track.min_width).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::new(config, (inner, outer), info.seed, info.generator))
+    }
+
+    /// Boundaries in the form `track_from_seed` returns them.
+    pub fn boundaries(&self) -> (Vec<Point>, Vec<Point>) {
+        (self.inner.clone(), self.outer.clone())
+    }
+}
+
+fn counter_clockwise(mut polygon: Vec<Point>) -> Vec<Point> {
+    if signed_area(&polygon) < 0.0 { polygon.reverse(); }
+    polygon
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "json")
+}
+
+fn check_version(version: u32) -> io::Result<()> {
+    if version == TRACK_VERSION { return Ok(()); }
+    Err(io::Error::new(io::ErrorKind::InvalidData,
+        format!("track version {} is not supported (expected {})", version, TRACK_VERSION)))
+}
+
+// turning between neighbouring centreline segments, divided by their mean length
+fn curvature(centreline: &Centreline) -> (f32, f32) {
+    let p = &centreline.points;
+    let n = p.len();
+    let (mut total, mut max) = (0.0, 0.0f32);
+    for i in 0..n {
+        let (a, b, c) = (&p[(i + n - 1) % n], &p[i], &p[(i+1) % n]);
+        let turn = ((get_angle(b, c) - get_angle(a, b) + PI).rem_euclid(2.0 * PI) - PI).abs();
+        total += turn;
+        max = max.max(turn / ((distance(a, b) + distance(b, c)) / 2.0).max(f32::EPSILON));
+    }
+    (total / centreline.length.max(f32::EPSILON), max)
+}
 
+// tries before falling back to an ellipse
+const GEN_ATTEMPTS: usize = 50;
 
-pub fn gen_track(grain: usize, res: usize, size: (Point, Point), width: f32) -> (Vec<Point>, Vec<Point>) {
+/// Shape of generated tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Generator {
+    /// Convex blob turning only one way.
+    ConvexHull,
+    /// Radius around the center varied by a few random waves, gives bends both ways.
+    Polar,
+    /// Convex hull with midpoints of edges pushed in or out, gives hairpins.
+    Displaced,
+}
 
-    let mut track: Vec<Point> = vec![];
+/// Why a track can't be driven.
+#[derive(Clone, Debug)]
+pub enum TrackError {
+    /// Boundary has no area, its points were collinear or duplicate.
+    Degenerate,
+    /// Boundary crosses itself.
+    SelfIntersection {
+        /// It's the outer one.
+        outer: bool,
+    },
+    /// Inner and outer boundaries cross each other.
+    BoundariesCross,
+    /// Inner boundary isn't inside the outer one.
+    NotNested,
+    /// Corridor is narrower than `track.min_width` somewhere.
+    TooNarrow {
+        /// Boundary vertex of the narrowest spot.
+        at: Point,
+        /// Distance from it to the other boundary.
+        width: f32,
+    },
+}
 
-    let mut rng = rand::rng();
-    for _ in 0..grain {
-        track.push(Point::new(rng.random_range(size.0.x..=size.1.x), rng.random_range(size.0.y..=size.1.y)));
+impl fmt::Display for TrackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackError::Degenerate => write!(f, "degenerate boundary"),
+            TrackError::SelfIntersection { outer } => write!(f, "{} boundary crosses itself", if *outer { "outer" } else { "inner" }),
+            TrackError::BoundariesCross => write!(f, "boundaries cross each other"),
+            TrackError::NotNested => write!(f, "inner boundary is not inside outer one"),
+            TrackError::TooNarrow { at, width } => write!(f, "only {:.1} wide at ({:.0}, {:.0})", width, at.x, at.y),
+        }
     }
-    track = calculate_convex_hull(&track);
-    track = chaikin_corner_cutting(&track, res);
-    let track2 = dualise(&track, width);
-    (track, track2)
 }
 
+impl std::error::Error for TrackError {}
 
+/// Checks both boundaries are simple, nested, and at least `min_width` apart everywhere.
+pub fn check_track(inner: &[Point], outer: &[Point], min_width: f32) -> Result<(), TrackError> {
+    if inner.len() < 3 || outer.len() < 3 || signed_area(inner).abs() < 1.0 || signed_area(outer).abs() < 1.0 {
+        return Err(TrackError::Degenerate);
+    }
+    if !is_simple(inner) { return Err(TrackError::SelfIntersection { outer: false }); }
+    if !is_simple(outer) { return Err(TrackError::SelfIntersection { outer: true }); }
+    if polygons_intersect(inner, outer) { return Err(TrackError::BoundariesCross); }
+    if !point_in_polygon(&inner[0], outer) { return Err(TrackError::NotNested); }
 
+    // narrowest spot is at a vertex of one of the boundaries
+    let narrowest = inner.iter().map(|p| (p, distance_to_polygon(p, outer)) )
+        .chain(outer.iter().map(|p| (p, distance_to_polygon(p, inner)) ))
+        .min_by(|a, b| a.1.total_cmp(&b.1) );
+    match narrowest {
+        Some((p, w)) if w < min_width => Err(TrackError::TooNarrow { at: p.clone(), width: w }),
+        _ => Ok(()),
+    }
+}
 
-fn calculate_convex_hull(points: &Vec<Point>) -> Vec<Point> {
+/// Same seed and config always give the same track.
+pub fn track_from_seed(t: &TrackConfig, seed: u64) -> (Vec<Point>, Vec<Point>) {
+    generate(t, seed).0
+}
+
+/// `track_from_seed` together with why every rejected attempt was rejected.
+/// Shapes are regenerated until `check_track` passes,
+/// after `GEN_ATTEMPTS` a plain ellipse is used, which is always valid.
+pub fn generate(t: &TrackConfig, seed: u64) -> ((Vec<Point>, Vec<Point>), Vec<TrackError>) {
+    let area = (Point::new(t.width, t.width), Point::new(t.size.0 - t.width, t.size.1 - t.width));
+    let rng = &mut ChaCha8Rng::seed_from_u64(seed);
+    let mut rejected = vec![];
+
+    for _ in 0..GEN_ATTEMPTS {
+        let mut outline = match t.generator {
+            Generator::ConvexHull => convex_outline(t.grain, &area, rng),
+            Generator::Polar => polar_outline(t.grain, t.roughness, &area, rng),
+            Generator::Displaced => displaced_outline(t.grain, t.roughness, &area, rng),
+        };
+        // repairs: duplicate points break normals, and dualise offsets to the left,
+        // so orientation has to match convex hull's
+        outline.dedup_by(|a, b| distance(a, b) < 0.001);
+        if signed_area(&outline) < 0.0 { outline.reverse(); }
+        let track = chaikin_corner_cutting(&outline, t.res);
+        // sharp inward bends make offset loop over itself, real boundary is what's left without those
+        let track2 = remove_loops(&dualise(&track, t.width), track.len() / 4);
+        match check_track(&track, &track2, t.min_width) {
+            Ok(()) => return ((track, track2), rejected),
+            Err(e) => rejected.push(e),
+        }
+    }
+    let track = chaikin_corner_cutting(&polar_outline(t.grain.max(8), 0.0, &area, rng), t.res);
+    let track2 = dualise(&track, t.width);
+    ((track, track2), rejected)
+}
+
+/// Convex hull of `grain` random points inside `area`.
+pub fn convex_outline(grain: usize, area: &(Point, Point), rng: &mut impl Rng) -> Vec<Point> {
+    let points: Vec<Point> = (0..grain).map(|_| Point::new(rng.random_range(area.0.x..=area.1.x), rng.random_range(area.0.y..=area.1.y)) ).collect();
+    calculate_convex_hull(&points)
+}
+
+/// `grain` points around the center of `area`, pulled in by up to `roughness` of its size.
+pub fn polar_outline(grain: usize, roughness: f32, area: &(Point, Point), rng: &mut impl Rng) -> Vec<Point> {
+    let c = area.0.mid(&area.1);
+    let (rx, ry) = ((area.1.x - area.0.x) / 2.0, (area.1.y - area.0.y) / 2.0);
+    // (frequency, amplitude, phase), higher frequencies are weaker so the shape stays smooth
+    let waves: Vec<(f32, f32, f32)> = (2..=5).map(|k| (k as f32, rng.random_range(0.2..1.0) / k as f32, rng.random_range(0.0..2.0*PI)) ).collect();
+    let max: f32 = waves.iter().map(|w| w.1 ).sum();
+
+    (0..grain).map(|i| {
+        let a = i as f32 / grain as f32 * 2.0 * PI;
+        let noise = waves.iter().map(|(k, amp, phase)| amp * (k * a + phase).sin() ).sum::<f32>() / max;
+        let r = 1.0 - roughness * (0.5 + 0.5 * noise);
+        Point::new(c.x + rx * r * a.cos(), c.y + ry * r * a.sin())
+    }).collect()
+}
+
+/// Convex hull of `grain` random points, every edge split twice with midpoint moved sideways
+/// by up to `roughness` of edge length, kept inside `area`.
+pub fn displaced_outline(grain: usize, roughness: f32, area: &(Point, Point), rng: &mut impl Rng) -> Vec<Point> {
+    let mut outline = convex_outline(grain, area, rng);
+    for _ in 0..2 {
+        let n = outline.len();
+        outline = (0..n).flat_map(|i| {
+            let (p, q) = (&outline[i], &outline[(i+1) % n]);
+            let m = move_perp(p, &p.mid(q), q, rng.random_range(-1.0..=1.0) * roughness * distance(p, q));
+            [p.clone(), Point::new(m.x.clamp(area.0.x, area.1.x), m.y.clamp(area.0.y, area.1.y))]
+        }).collect();
+    }
+    outline
+}
+
+/// Gift wrapping, hull is returned in clockwise order.
+pub fn calculate_convex_hull(points: &[Point]) -> Vec<Point> {
     //There must be at least 3 points
-    if points.len() < 3 { return points.clone(); }
+    if points.len() < 3 { return points.to_vec(); }
 
     let mut hull = vec![];
 
@@ -63,7 +375,8 @@ fn orientation(p: &Point, q: &Point, r: &Point) -> usize {
     if val > 0. { 1 } else { 2 }
 }
 
-fn chaikin_corner_cutting(points: &[Point], iterations: usize) -> Vec<Point> {
+/// Smooths closed polygon, every iteration doubles amount of points.
+pub fn chaikin_corner_cutting(points: &[Point], iterations: usize) -> Vec<Point> {
     let mut points = points.to_owned();
     for _ in 0..iterations {
         let mut result = vec![];
@@ -80,12 +393,115 @@ fn chaikin_corner_cutting(points: &[Point], iterations: usize) -> Vec<Point> {
     points
 }
 
-fn dualise(points: &[Point], distance: f32) -> Vec<Point> {
+/// Offsets every point of closed polygon by `distance` along its normal.
+pub fn dualise(points: &[Point], distance: f32) -> Vec<Point> {
+    dualise_varying(points, &vec![distance; points.len()])
+}
+
+/// `dualise` with own distance for every point.
+pub fn dualise_varying(points: &[Point], distances: &[f32]) -> Vec<Point> {
 let mut track: Vec<Point> = vec![];
 
     for i in 1..points.len()+1 {
+        let distance = distances[i%points.len()];
         let angle = (points[(i+1)%points.len()].y - points[i-1].y).atan2(points[(i+1)%points.len()].x - points[i-1].x);
         track.push(Point::new(points[i%points.len()].x+distance*angle.sin(), points[i%points.len()].y-distance*angle.cos()));
     }
     track
 }
+
+/// Boundaries around closed centreline through `points`, `widths` wide at each of them.
+/// Both are smoothed `res` times first, like generated tracks.
+pub fn from_centreline(points: &[Point], widths: &[f32], res: usize) -> (Vec<Point>, Vec<Point>) {
+    let (mut points, mut widths) = (points.to_vec(), widths.to_vec());
+    // offsets have to go outwards for the outer boundary, whichever way points were placed
+    if signed_area(&points) < 0.0 { points.reverse(); widths.reverse(); }
+    let centre = chaikin_corner_cutting(&points, res);
+    // widths are smoothed the same way as points, so they stay paired
+    let widths: Vec<f32> = chaikin_corner_cutting(&widths.iter().map(|w| Point::new(*w, 0.0) ).collect::<Vec<_>>(), res)
+        .iter().map(|p| p.x / 2.0 ).collect();
+
+    let span = centre.len() / 4;
+    let inner = remove_loops(&dualise_varying(&centre, &widths.iter().map(|w| -w ).collect::<Vec<_>>()), span);
+    let outer = remove_loops(&dualise_varying(&centre, &widths), span);
+    (inner, outer)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(c: f32, half: f32) -> Vec<Point> {
+        vec![Point::new(c - half, c - half), Point::new(c + half, c - half), Point::new(c + half, c + half), Point::new(c - half, c + half)]
+    }
+
+    #[test]
+    fn clockwise_boundaries_start_on_the_track() {
+        let config = Config::default();
+        let (inner, outer) = (square(200., 50.), square(200., 150.));
+        let a = Track::new(&config, (inner.clone(), outer.clone()), 1, None);
+        let b = Track::new(&config, (inner.into_iter().rev().collect(), outer.into_iter().rev().collect()), 1, None);
+        assert!(distance(&a.start.0, &b.start.0) < 1e-3);
+        assert!((a.start.1 - b.start.1).abs() < 1e-3);
+        assert!(point_in_polygon(&b.start.0, &b.outer) && !point_in_polygon(&b.start.0, &b.inner));
+    }
+
+    #[test]
+    fn generators_find_valid_tracks_before_falling_back() {
+        for generator in [Generator::Polar, Generator::Displaced] {
+            let t = TrackConfig { generator, ..Config::default().track };
+            for seed in 0..20 {
+                let rejected = generate(&t, seed).1;
+                assert!(rejected.len() < GEN_ATTEMPTS, "{:?} fell back to ellipse on seed {}", generator, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn centreline_gives_same_track_either_way_round() {
+        let points = square(200., 100.);
+        let widths = [40., 40., 80., 80.];
+        let (inner, outer) = from_centreline(&points, &widths, 3);
+        let rev: Vec<f32> = widths.iter().rev().copied().collect();
+        let (inner_rev, outer_rev) = from_centreline(&points.iter().rev().cloned().collect::<Vec<_>>(), &rev, 3);
+
+        assert!(check_track(&inner, &outer, 30.).is_ok());
+        assert!(check_track(&inner_rev, &outer_rev, 30.).is_ok());
+        assert!((signed_area(&outer) - signed_area(&outer_rev)).abs() < 1.);
+        assert!((signed_area(&inner) - signed_area(&inner_rev)).abs() < 1.);
+    }
+
+    #[test]
+    fn nested_squares_are_a_track() {
+        assert!(check_track(&square(200., 50.), &square(200., 150.), 40.).is_ok());
+    }
+
+    #[test]
+    fn figure_eight_is_rejected() {
+        let eight = vec![Point::new(100., 100.), Point::new(300., 300.), Point::new(300., 100.), Point::new(100., 250.)];
+        assert!(matches!(check_track(&eight, &square(200., 150.), 40.), Err(TrackError::SelfIntersection { outer: false })));
+        assert!(matches!(check_track(&square(200., 50.), &eight, 40.), Err(TrackError::SelfIntersection { outer: true })));
+    }
+
+    #[test]
+    fn narrow_corridor_is_rejected() {
+        match check_track(&square(200., 50.), &square(200., 70.), 40.) {
+            Err(TrackError::TooNarrow { width, .. }) => assert!((width - 20.).abs() < 1e-3),
+            r => panic!("expected TooNarrow, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn boundaries_side_by_side_are_rejected() {
+        assert!(matches!(check_track(&square(100., 50.), &square(400., 50.), 40.), Err(TrackError::NotNested)));
+    }
+
+    #[test]
+    fn crossing_and_flat_boundaries_are_rejected() {
+        let shifted: Vec<Point> = square(200., 100.).iter().map(|p| Point::new(p.x + 150., p.y + 50.) ).collect();
+        assert!(matches!(check_track(&square(200., 100.), &shifted, 40.), Err(TrackError::BoundariesCross)));
+        let flat = vec![Point::new(0., 0.), Point::new(1., 0.), Point::new(2., 0.)];
+        assert!(matches!(check_track(&flat, &square(200., 150.), 40.), Err(TrackError::Degenerate)));
+    }
+}
@@ -0,0 +1,73 @@
+//! Held-out tracks, to tell driving from memorising.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use rusty_neat::NN;
+use serde::{Serialize, Deserialize};
+
+use crate::config::Config;
+use crate::fitness::FitnessFunction;
+use crate::simulation::Simulation;
+use crate::track::Track;
+
+
+/// Champion's driving on validation tracks, separate from training fitness.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValidationResult {
+    /// Generation champion is from.
+    pub generation: usize,
+    /// Fraction of tracks with at least one full lap.
+    pub completion_rate: f32,
+    /// Mean first lap time over completed tracks, `None` if none was completed.
+    pub mean_lap_time: Option<f32>,
+    /// Fraction of tracks that ended by hitting a wall.
+    pub crash_rate: f32,
+    /// Raw fitness averaged over all tracks.
+    pub mean_fitness: f32,
+}
+
+/// Fixed tracks from `validation` config, built once per run.
+pub struct ValidationSuite {
+    /// Seeded tracks first, then files.
+    pub tracks: Vec<Track>,
+}
+
+impl ValidationSuite {
+    /// Generates `validation.seeds` tracks and loads `validation.files`.
+    pub fn new(config: &Config) -> io::Result<Self> {
+        let mut tracks: Vec<Track> = config.validation.seeds.iter().map(|s| Track::from_seed(config, *s) ).collect();
+        for f in &config.validation.files {
+            tracks.push(Track::load(config, Path::new(f)).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", f, e)))?);
+        }
+        Ok(Self { tracks })
+    }
+
+    /// Drives `nn` alone on every track, with validation time limit, scored by training's `fitness`.
+    pub fn evaluate(&self, config: &Config, fitness: Arc<dyn FitnessFunction>, nn: &NN, generation: usize) -> ValidationResult {
+        let mut config = config.clone();
+        config.population.gen_len = config.validation.time_limit;
+        config.population.gen_len_growth = 0.0;
+
+        let (mut completed, mut crashed, mut total, mut lap_times) = (0, 0, 0.0, vec![]);
+        for t in &self.tracks {
+            let mut sim = Simulation::with_agents(config.clone(), vec![nn.clone()], t.clone());
+            sim.set_fitness(fitness.clone());
+            sim.run_episode(config.population.dt);
+            let c = &sim.cars[0];
+            if let Some(t) = c.lap_times.first() { completed += 1; lap_times.push(*t); }
+            if c.crashed() { crashed += 1; }
+            total += sim.fitness_of(c);
+        }
+
+        let n = self.tracks.len().max(1) as f32;
+        ValidationResult {
+            generation,
+            completion_rate: completed as f32 / n,
+            mean_lap_time: (!lap_times.is_empty()).then(|| lap_times.iter().sum::<f32>() / lap_times.len() as f32 ),
+            crash_rate: crashed as f32 / n,
+            mean_fitness: total / n,
+        }
+    }
+}
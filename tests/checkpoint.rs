@@ -0,0 +1,46 @@
+use std::fs;
+
+use neat_race::{Checkpoint, Simulation};
+
+mod common;
+use common::small_config;
+
+#[test]
+fn checkpoint_survives_save_and_load() {
+    let mut sim = Simulation::new(small_config(), 11).unwrap();
+    let dt = sim.config.population.dt;
+    sim.run_generation(dt);
+    let saved = sim.checkpoint();
+
+    let path = std::env::temp_dir().join(format!("neat_race_checkpoint_{}.ckpt", std::process::id()));
+    saved.save(&path).unwrap();
+    let loaded = Checkpoint::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let mut restored = Simulation::from_checkpoint(loaded).unwrap();
+    assert_eq!(restored.generation, sim.generation);
+    assert_eq!(restored.seed, sim.seed);
+    assert_eq!(restored.track.info.seed, sim.track.info.seed);
+    assert_eq!(restored.track.inner.len(), sim.track.inner.len());
+    assert_eq!(restored.neat.agents.len(), sim.neat.agents.len());
+    for (a, b) in restored.neat.agents.iter().zip(&sim.neat.agents) {
+        assert_eq!(a.nodes.len(), b.nodes.len());
+        assert_eq!(a.connections.len(), b.connections.len());
+    }
+    assert_eq!(restored.neat.innov_id, sim.neat.innov_id);
+    assert_eq!(restored.hall_of_fame.entries.len(), sim.hall_of_fame.entries.len());
+    assert!(restored.checkpoint().rng == saved.rng);
+
+    // both continue on the same tracks
+    assert_eq!(restored.run_generation(dt).track_seed, sim.run_generation(dt).track_seed);
+    assert_eq!(restored.track.info.seed, sim.track.info.seed);
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let path = std::env::temp_dir().join(format!("neat_race_bad_checkpoint_{}.ckpt", std::process::id()));
+    fs::write(&path, bincode::serialize(&0u32).unwrap()).unwrap();
+    let err = Checkpoint::load(&path).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(err.to_string().contains("version"));
+}
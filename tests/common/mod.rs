@@ -0,0 +1,10 @@
+use neat_race::Config;
+
+/// Small population and short generations, so tests run quickly.
+pub fn small_config() -> Config {
+    let mut config = Config::default();
+    config.population.entities = 20;
+    config.population.gen_len = 3.0;
+    config.hall_of_fame.size = 3;
+    config
+}
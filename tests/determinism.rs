@@ -0,0 +1,22 @@
+use neat_race::Simulation;
+
+mod common;
+use common::small_config;
+
+#[test]
+fn same_seed_gives_same_run() {
+    let mut a = Simulation::new(small_config(), 7).unwrap();
+    let mut b = Simulation::new(small_config(), 7).unwrap();
+    let dt = a.config.population.dt;
+    for _ in 0..3 {
+        let (sa, sb) = (a.run_generation(dt), b.run_generation(dt));
+        assert_eq!(sa.track_seed, sb.track_seed);
+        assert_eq!(sa.best_fitness.to_bits(), sb.best_fitness.to_bits());
+        assert_eq!(sa.mean_fitness.to_bits(), sb.mean_fitness.to_bits());
+        assert_eq!(sa.median_fitness.to_bits(), sb.median_fitness.to_bits());
+        assert_eq!(sa.species_sizes, sb.species_sizes);
+    }
+    for (x, y) in a.neat.agents.iter().zip(&b.neat.agents) {
+        assert_eq!(x.connections, y.connections);
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use neat_race::car::Car;
+use neat_race::config::WrongWay;
+use neat_race::fitness::{FitnessContext, FitnessFunction};
+use neat_race::validation::ValidationSuite;
+use neat_race::Simulation;
+
+mod common;
+use common::small_config;
+
+struct Constant;
+
+impl FitnessFunction for Constant {
+    fn fitness(&self, _: &Car, _: &FitnessContext) -> f32 {
+        5.0
+    }
+}
+
+#[test]
+fn custom_fitness_scores_hall_of_fame_and_validation() {
+    let mut config = small_config();
+    config.population.wrong_way = WrongWay::Ignore;
+    config.validation.seeds = vec![1, 2];
+    config.validation.time_limit = 3.0;
+
+    let mut sim = Simulation::new(config.clone(), 4).unwrap();
+    sim.set_fitness(Arc::new(Constant));
+    let dt = sim.config.population.dt;
+    sim.run_generation(dt);
+    sim.run_generation(dt);
+    assert!(!sim.hall_of_fame.entries.is_empty());
+    assert!(sim.hall_of_fame.entries.iter().all(|e| e.evaluations > 1 && e.mean_fitness == 5.0 ));
+
+    let suite = ValidationSuite::new(&config).unwrap();
+    let result = suite.evaluate(&config, sim.fitness(), &sim.champion().nn, sim.generation);
+    assert_eq!(result.mean_fitness, 5.0);
+}
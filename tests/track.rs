@@ -0,0 +1,62 @@
+use std::fs;
+use std::path::PathBuf;
+
+use neat_race::Config;
+use neat_race::geometry::Point;
+use neat_race::track::Track;
+
+fn temp(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("neat_race_{}_{}", std::process::id(), name))
+}
+
+fn same_points(a: &[Point], b: &[Point]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(p, q)| p.x == q.x && p.y == q.y )
+}
+
+fn round_trip(name: &str) {
+    let config = Config::default();
+    let track = Track::from_seed(&config, 21);
+    let path = temp(name);
+    track.save(&path).unwrap();
+    let loaded = Track::load(&config, &path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(same_points(&loaded.inner, &track.inner));
+    assert!(same_points(&loaded.outer, &track.outer));
+    assert_eq!(loaded.info.seed, track.info.seed);
+    assert_eq!(loaded.info.generator, track.info.generator);
+    assert_eq!(loaded.info.length, track.info.length);
+    assert_eq!(loaded.info.min_width, track.info.min_width);
+    assert_eq!(loaded.direction, track.direction);
+}
+
+#[test]
+fn json_track_survives_save_and_load() {
+    round_trip("track.json");
+}
+
+#[test]
+fn bincode_track_survives_save_and_load() {
+    round_trip("track.bin");
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let config = Config::default();
+    let track = Track::from_seed(&config, 21);
+
+    let path = temp("bad_track.json");
+    track.save(&path).unwrap();
+    let mut json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    json["version"] = 0.into();
+    fs::write(&path, json.to_string()).unwrap();
+    let err = Track::load(&config, &path).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(err.to_string().contains("version"));
+
+    let path = temp("bad_track.bin");
+    fs::write(&path, bincode::serialize(&0u32).unwrap()).unwrap();
+    let err = Track::load(&config, &path).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(err.to_string().contains("version"));
+}